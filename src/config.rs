@@ -1,13 +1,17 @@
 use std::{
+    collections::HashMap,
     fs::{File, create_dir_all},
     io::Write,
     sync::RwLock,
 };
 
-use crate::{scrcpy::media::VideoCodec, utils::relate_to_data_path};
+use crate::{
+    mask::mapping::utils::Humanize, scrcpy::media::VideoCodec, utils::relate_to_data_path,
+};
 use once_cell::sync::Lazy;
 use paste::paste;
 use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::to_string_pretty;
 
@@ -15,7 +19,7 @@ static CONFIG: Lazy<RwLock<LocalConfig>> = Lazy::new(|| RwLock::default());
 
 // TODO 单独写外部脚本来捕获特定窗口，发送Post消息来设置蒙版相关配置（宽度>=高度则设置横屏相关配置，否则设置竖屏）
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct LocalConfig {
     // port
@@ -32,6 +36,12 @@ pub struct LocalConfig {
     // mapping
     pub active_mapping_file: String,
     pub mapping_label_opacity: f32,
+    pub rotation_mapping: RotationMapping,
+    pub scroll_forwarding: ScrollForwarding,
+    // used by mappings without their own humanization
+    pub humanize: Humanize,
+    // fixed seed to reproduce the humanization, random if not set
+    pub humanize_seed: Option<u64>,
     // language
     pub language: String,
     // clipboard sync
@@ -41,6 +51,10 @@ pub struct LocalConfig {
     pub video_bit_rate: u32,
     pub video_max_size: u32,
     pub video_max_fps: u32,
+    // per-device profiles, keyed by device serial
+    pub device_profiles: HashMap<String, DeviceProfile>,
+    // device groups controlled together, keyed by group name
+    pub device_groups: HashMap<String, Vec<String>>,
 }
 
 impl Default for LocalConfig {
@@ -56,16 +70,182 @@ impl Default for LocalConfig {
             horizontal_position: (100, 100),
             active_mapping_file: "default.json".to_string(),
             mapping_label_opacity: 0.3,
+            rotation_mapping: RotationMapping::default(),
+            scroll_forwarding: ScrollForwarding::default(),
+            humanize: Humanize::default(),
+            humanize_seed: None,
             language: "en-US".to_string(),
             clipboard_sync: true,
             video_codec: VideoCodec::H264,
             video_bit_rate: 8_000000, // 8M
             video_max_size: 0,        // default no limit
             video_max_fps: 0,         // default no limit
+            device_profiles: HashMap::new(),
+            device_groups: HashMap::new(),
+        }
+    }
+}
+
+/// Overrides applied on top of the global config for a specific device.
+/// Fields left as `None` fall back to the global value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeviceProfile {
+    // mask
+    pub vertical_mask_height: Option<u32>,
+    pub horizontal_mask_width: Option<u32>,
+    pub vertical_position: Option<(i32, i32)>,
+    pub horizontal_position: Option<(i32, i32)>,
+    // mapping
+    pub active_mapping_file: Option<String>,
+    pub rotation_mapping: Option<RotationMapping>,
+    // video config
+    pub video_codec: Option<VideoCodec>,
+    pub video_bit_rate: Option<u32>,
+    pub video_max_size: Option<u32>,
+    pub video_max_fps: Option<u32>,
+    // group control
+    pub calibration: Option<DeviceCalibration>,
+}
+
+/// Mapping files activated automatically when the main device rotates.
+/// A file bound to the exact rotation takes precedence over the orientation one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RotationMapping {
+    pub portrait: Option<String>,
+    pub landscape: Option<String>,
+    // keyed by rotation (0-3), 90 degrees per step
+    pub rotations: HashMap<u16, String>,
+}
+
+impl RotationMapping {
+    pub fn resolve(&self, rotation: u16, width: u32, height: u32) -> Option<&String> {
+        self.rotations.get(&rotation).or(if width >= height {
+            self.landscape.as_ref()
+        } else {
+            self.portrait.as_ref()
+        })
+    }
+
+    pub fn files_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        self.portrait
+            .iter_mut()
+            .chain(self.landscape.iter_mut())
+            .chain(self.rotations.values_mut())
+    }
+}
+
+/// Mouse wheel forwarded to the device at the cursor in normal mapping mode,
+/// unless a mapping is bound to the wheel.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ScrollForwarding {
+    pub enable: bool,
+    // scroll steps sent for each wheel notch
+    pub scale: f32,
+    // scroll horizontally while shift is held
+    pub shift_horizontal: bool,
+}
+
+impl Default for ScrollForwarding {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            scale: 1.,
+            shift_horizontal: true,
+        }
+    }
+}
+
+/// How the mask coordinate space is fitted into a device with a different aspect ratio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum FitMode {
+    /// scale each axis independently
+    #[default]
+    Stretch,
+    /// keep aspect ratio, leave bars on the longer axis of the device
+    Letterbox,
+    /// keep aspect ratio, cut off the overflowing part
+    Crop,
+}
+
+/// Affine calibration applied to touch and scroll events sent to a device.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DeviceCalibration {
+    pub fit: FitMode,
+    // applied after fitting, in device pixels
+    pub scale: (f32, f32),
+    pub offset: (f32, f32),
+    // delay before events are sent to device
+    pub delay_ms: u64,
+}
+
+impl Default for DeviceCalibration {
+    fn default() -> Self {
+        Self {
+            fit: FitMode::Stretch,
+            scale: (1., 1.),
+            offset: (0., 0.),
+            delay_ms: 0,
         }
     }
 }
 
+impl DeviceCalibration {
+    /// Map a position in a `src` sized space to the `dst` sized device.
+    pub fn transform(&self, pos: (i32, i32), src: (u16, u16), dst: (u32, u32)) -> (i32, i32) {
+        let (src_w, src_h) = (src.0.max(1) as f32, src.1.max(1) as f32);
+        let (dst_w, dst_h) = (dst.0 as f32, dst.1 as f32);
+        let (sx, sy) = (dst_w / src_w, dst_h / src_h);
+        let (sx, sy, left, top) = match self.fit {
+            FitMode::Stretch => (sx, sy, 0., 0.),
+            FitMode::Letterbox | FitMode::Crop => {
+                let s = if self.fit == FitMode::Letterbox {
+                    sx.min(sy)
+                } else {
+                    sx.max(sy)
+                };
+                (s, s, (dst_w - src_w * s) / 2., (dst_h - src_h * s) / 2.)
+            }
+        };
+        let x = (pos.0 as f32 * sx + left) * self.scale.0 + self.offset.0;
+        let y = (pos.1 as f32 * sy + top) * self.scale.1 + self.offset.1;
+        (
+            (x.round() as i32).clamp(0, (dst.0 as i32 - 1).max(0)),
+            (y.round() as i32).clamp(0, (dst.1 as i32 - 1).max(0)),
+        )
+    }
+}
+
+macro_rules! define_profile_apply {
+    ($($field:ident),* $(,)?) => {
+        impl DeviceProfile {
+            pub fn apply(&self, config: &mut LocalConfig) {
+                $(
+                    if let Some(value) = &self.$field {
+                        config.$field = value.clone();
+                    }
+                )*
+            }
+        }
+    };
+}
+
+define_profile_apply!(
+    vertical_mask_height,
+    horizontal_mask_width,
+    vertical_position,
+    horizontal_position,
+    active_mapping_file,
+    rotation_mapping,
+    video_codec,
+    video_bit_rate,
+    video_max_size,
+    video_max_fps,
+);
+
 macro_rules! define_setter {
     ($(($field:ident, $typ:ty)),* $(,)?) => {
         paste! {
@@ -116,6 +296,73 @@ impl LocalConfig {
         CONFIG.read().unwrap().clone()
     }
 
+    /// Get the config with the profile of the given device (if any) applied
+    pub fn get_for_device(device_id: &str) -> LocalConfig {
+        let mut config = Self::get();
+        if let Some(profile) = config.device_profiles.get(device_id).cloned() {
+            profile.apply(&mut config);
+        }
+        config
+    }
+
+    pub fn get_device_profile(device_id: &str) -> Option<DeviceProfile> {
+        CONFIG
+            .read()
+            .unwrap()
+            .device_profiles
+            .get(device_id)
+            .cloned()
+    }
+
+    pub fn set_device_profile(device_id: String, profile: DeviceProfile) {
+        CONFIG
+            .write()
+            .unwrap()
+            .device_profiles
+            .insert(device_id, profile);
+        Self::save().unwrap();
+    }
+
+    pub fn remove_device_profile(device_id: &str) -> bool {
+        let removed = CONFIG
+            .write()
+            .unwrap()
+            .device_profiles
+            .remove(device_id)
+            .is_some();
+        if removed {
+            Self::save().unwrap();
+        }
+        removed
+    }
+
+    pub fn get_device_calibration(device_id: &str) -> DeviceCalibration {
+        CONFIG
+            .read()
+            .unwrap()
+            .device_profiles
+            .get(device_id)
+            .and_then(|profile| profile.calibration.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_device_group(name: &str) -> Option<Vec<String>> {
+        CONFIG.read().unwrap().device_groups.get(name).cloned()
+    }
+
+    pub fn set_device_group(name: String, devices: Vec<String>) {
+        CONFIG.write().unwrap().device_groups.insert(name, devices);
+        Self::save().unwrap();
+    }
+
+    pub fn remove_device_group(name: &str) -> bool {
+        let removed = CONFIG.write().unwrap().device_groups.remove(name).is_some();
+        if removed {
+            Self::save().unwrap();
+        }
+        removed
+    }
+
     pub fn get_clipboard_sync() -> bool {
         CONFIG.read().unwrap().clipboard_sync
     }
@@ -131,6 +378,10 @@ impl LocalConfig {
         (horizontal_position, (i32, i32)),
         (active_mapping_file, String),
         (mapping_label_opacity, f32),
+        (rotation_mapping, RotationMapping),
+        (scroll_forwarding, ScrollForwarding),
+        (humanize, Humanize),
+        (humanize_seed, Option<u64>),
         (language, String),
         (clipboard_sync, bool),
         (video_codec, VideoCodec),
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{borrow::Cow, str::FromStr};
 
 use bevy::input::{
     gamepad::{GamepadAxis, GamepadButton},
@@ -10,6 +10,7 @@ use bevy_ineffable::{
     prelude::{DualAxisBinding, InputBinding, SingleAxisBinding},
 };
 use rust_i18n::t;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone)]
@@ -55,6 +56,66 @@ impl From<MergedButton> for BinaryInput {
     }
 }
 
+// Buttons with a name, passed to `$mac` after `$args`.
+// Shared by parsing, printing and the json schema, so that they are always in sync.
+macro_rules! with_named_gamepad_buttons {
+    ($mac:ident!($($args:tt)*)) => {
+        $mac!($($args)*
+            South, East, North, West, C, Z,
+            LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
+            Select, Start, Mode,
+            LeftThumb, RightThumb,
+            DPadUp, DPadDown, DPadLeft, DPadRight
+        )
+    };
+}
+
+macro_rules! with_named_keycodes {
+    ($mac:ident!($($args:tt)*)) => {
+        $mac!($($args)*
+            Backquote, Backslash, BracketLeft, BracketRight, Comma,
+            Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+            Equal, IntlBackslash, IntlRo, IntlYen,
+            KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ,
+            KeyK, KeyL, KeyM, KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT,
+            KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+            Minus, Period, Quote, Semicolon, Slash,
+            AltLeft, AltRight, Backspace, CapsLock, ContextMenu,
+            ControlLeft, ControlRight, Enter, SuperLeft, SuperRight,
+            ShiftLeft, ShiftRight, Space, Tab,
+            Convert, KanaMode, Lang1, Lang2, Lang3, Lang4, Lang5, NonConvert,
+            Delete, End, Help, Home, Insert, PageDown, PageUp,
+            ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
+            NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
+            Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+            NumpadAdd, NumpadBackspace, NumpadClear, NumpadClearEntry, NumpadComma,
+            NumpadDecimal, NumpadDivide, NumpadEnter, NumpadEqual, NumpadHash,
+            NumpadMemoryAdd, NumpadMemoryClear, NumpadMemoryRecall,
+            NumpadMemoryStore, NumpadMemorySubtract, NumpadMultiply,
+            NumpadParenLeft, NumpadParenRight, NumpadStar, NumpadSubtract,
+            Escape, Fn, FnLock, PrintScreen, ScrollLock, Pause,
+            BrowserBack, BrowserFavorites, BrowserForward, BrowserHome, BrowserRefresh,
+            BrowserSearch, BrowserStop, Eject, LaunchApp1, LaunchApp2, LaunchMail,
+            MediaPlayPause, MediaSelect, MediaStop, MediaTrackNext, MediaTrackPrevious,
+            Power, Sleep, AudioVolumeDown, AudioVolumeMute, AudioVolumeUp, WakeUp,
+            Meta, Hyper, Turbo, Abort, Resume, Suspend, Again, Copy, Cut, Find, Open,
+            Paste, Props, Select, Undo, Hiragana, Katakana,
+            F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+            F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25,
+            F26, F27, F28, F29, F30, F31, F32, F33, F34, F35
+        )
+    };
+}
+
+macro_rules! button_names {
+    ($prefix:literal; $($variant:ident),* $(,)?) => {
+        &[$(concat!($prefix, stringify!($variant))),*]
+    };
+}
+
+const GAMEPAD_BUTTON_NAMES: &[&str] = with_named_gamepad_buttons!(button_names!("G-";));
+const KEYCODE_NAMES: &[&str] = with_named_keycodes!(button_names!("";));
+
 macro_rules! match_gamepad_to_string {
     ($button:expr; $($variant:ident),* $(,)?) => {
         match $button {
@@ -92,45 +153,12 @@ impl ToString for MergedButton {
             },
             MergedButton::ScrollDown => "ScrollDown".to_string(),
             MergedButton::ScrollUp => "ScrollUp".to_string(),
-            MergedButton::GamePad(gamepad_button) => match_gamepad_to_string!(gamepad_button;
-                South, East, North, West, C, Z,
-                LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
-                Select, Start, Mode,
-                LeftThumb, RightThumb,
-                DPadUp, DPadDown, DPadLeft, DPadRight
-            ),
-            MergedButton::Keyboard(key_code) => match_keycode_to_string!(key_code;
-                Backquote, Backslash, BracketLeft, BracketRight, Comma,
-                Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
-                Equal, IntlBackslash, IntlRo, IntlYen,
-                KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ,
-                KeyK, KeyL, KeyM, KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT,
-                KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
-                Minus, Period, Quote, Semicolon, Slash,
-                AltLeft, AltRight, Backspace, CapsLock, ContextMenu,
-                ControlLeft, ControlRight, Enter, SuperLeft, SuperRight,
-                ShiftLeft, ShiftRight, Space, Tab,
-                Convert, KanaMode, Lang1, Lang2, Lang3, Lang4, Lang5, NonConvert,
-                Delete, End, Help, Home, Insert, PageDown, PageUp,
-                ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
-                NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
-                Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
-                NumpadAdd, NumpadBackspace, NumpadClear, NumpadClearEntry, NumpadComma,
-                NumpadDecimal, NumpadDivide, NumpadEnter, NumpadEqual, NumpadHash,
-                NumpadMemoryAdd, NumpadMemoryClear, NumpadMemoryRecall,
-                NumpadMemoryStore, NumpadMemorySubtract, NumpadMultiply,
-                NumpadParenLeft, NumpadParenRight, NumpadStar, NumpadSubtract,
-                Escape, Fn, FnLock, PrintScreen, ScrollLock, Pause,
-                BrowserBack, BrowserFavorites, BrowserForward, BrowserHome, BrowserRefresh,
-                BrowserSearch, BrowserStop, Eject, LaunchApp1, LaunchApp2, LaunchMail,
-                MediaPlayPause, MediaSelect, MediaStop, MediaTrackNext, MediaTrackPrevious,
-                Power, Sleep, AudioVolumeDown, AudioVolumeMute, AudioVolumeUp, WakeUp,
-                Meta, Hyper, Turbo, Abort, Resume, Suspend, Again, Copy, Cut, Find, Open,
-                Paste, Props, Select, Undo, Hiragana, Katakana,
-                F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
-                F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25,
-                F26, F27, F28, F29, F30, F31, F32, F33, F34, F35
-            ),
+            MergedButton::GamePad(gamepad_button) => {
+                with_named_gamepad_buttons!(match_gamepad_to_string!(gamepad_button;))
+            }
+            MergedButton::Keyboard(key_code) => {
+                with_named_keycodes!(match_keycode_to_string!(key_code;))
+            }
         }
     }
 }
@@ -198,46 +226,9 @@ impl FromStr for MergedButton {
                 _ => Err(format!("{}: {}", t!("mask.mapping.unknownMouseButton"), s)),
             }
         } else if let Some(stripped) = s.strip_prefix("G-") {
-            match_gamepad_from_str!(s, stripped;
-                South, East, North, West, C, Z,
-                LeftTrigger, LeftTrigger2, RightTrigger, RightTrigger2,
-                Select, Start, Mode,
-                LeftThumb, RightThumb,
-                DPadUp, DPadDown, DPadLeft, DPadRight
-            )
+            with_named_gamepad_buttons!(match_gamepad_from_str!(s, stripped;))
         } else {
-            match_keycode_from_str!(s;
-                Backquote, Backslash, BracketLeft, BracketRight, Comma,
-                Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
-                Equal, IntlBackslash, IntlRo, IntlYen,
-                KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ,
-                KeyK, KeyL, KeyM, KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT,
-                KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
-                Minus, Period, Quote, Semicolon, Slash,
-                AltLeft, AltRight, Backspace, CapsLock, ContextMenu,
-                ControlLeft, ControlRight, Enter, SuperLeft, SuperRight,
-                ShiftLeft, ShiftRight, Space, Tab,
-                Convert, KanaMode, Lang1, Lang2, Lang3, Lang4, Lang5, NonConvert,
-                Delete, End, Help, Home, Insert, PageDown, PageUp,
-                ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
-                NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
-                Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
-                NumpadAdd, NumpadBackspace, NumpadClear, NumpadClearEntry, NumpadComma,
-                NumpadDecimal, NumpadDivide, NumpadEnter, NumpadEqual, NumpadHash,
-                NumpadMemoryAdd, NumpadMemoryClear, NumpadMemoryRecall,
-                NumpadMemoryStore, NumpadMemorySubtract, NumpadMultiply,
-                NumpadParenLeft, NumpadParenRight, NumpadStar, NumpadSubtract,
-                Escape, Fn, FnLock, PrintScreen, ScrollLock, Pause,
-                BrowserBack, BrowserFavorites, BrowserForward, BrowserHome, BrowserRefresh,
-                BrowserSearch, BrowserStop, Eject, LaunchApp1, LaunchApp2, LaunchMail,
-                MediaPlayPause, MediaSelect, MediaStop, MediaTrackNext, MediaTrackPrevious,
-                Power, Sleep, AudioVolumeDown, AudioVolumeMute, AudioVolumeUp, WakeUp,
-                Meta, Hyper, Turbo, Abort, Resume, Suspend, Again, Copy, Cut, Find, Open,
-                Paste, Props, Select, Undo, Hiragana, Katakana,
-                F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
-                F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25,
-                F26, F27, F28, F29, F30, F31, F32, F33, F34, F35
-            )
+            with_named_keycodes!(match_keycode_from_str!(s;))
         }
     }
 }
@@ -277,13 +268,46 @@ impl<'de> Deserialize<'de> for MergedButton {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl JsonSchema for MergedButton {
+    fn schema_name() -> Cow<'static, str> {
+        "MergedButton".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        let mut names = vec![
+            "M-Left",
+            "M-Right",
+            "M-Middle",
+            "M-Back",
+            "M-Forward",
+            "ScrollDown",
+            "ScrollUp",
+            "Unidentified",
+        ];
+        names.extend(GAMEPAD_BUTTON_NAMES);
+        names.extend(KEYCODE_NAMES);
+        json_schema!({
+            "type": "string",
+            "anyOf": [
+                { "enum": names },
+                // buttons and keys without a name are given by code
+                { "pattern": "^(M-Other-|G-Other-|Android-|MacOS-|Windows-|Xkb-)[0-9]+$" }
+            ]
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ButtonBinding(Vec<MergedButton>);
 
 impl ButtonBinding {
     pub fn new(buttons: Vec<MergedButton>) -> Self {
         ButtonBinding(buttons)
     }
+
+    pub fn buttons(&self) -> &[MergedButton] {
+        &self.0
+    }
 }
 
 impl ToString for ButtonBinding {
@@ -311,7 +335,7 @@ impl From<ButtonBinding> for ChordLike {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "type")]
 pub enum DirectionBinding {
     Button {
@@ -321,11 +345,30 @@ pub enum DirectionBinding {
         right: ButtonBinding,
     },
     JoyStick {
+        #[schemars(schema_with = "gamepad_axis_schema")]
         x: GamepadAxis,
+        #[schemars(schema_with = "gamepad_axis_schema")]
         y: GamepadAxis,
     },
 }
 
+fn gamepad_axis_schema(_generator: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "oneOf": [
+            {
+                "type": "string",
+                "enum": ["LeftStickX", "LeftStickY", "LeftZ", "RightStickX", "RightStickY", "RightZ"]
+            },
+            {
+                "type": "object",
+                "properties": { "Other": { "type": "integer", "minimum": 0, "maximum": 255 } },
+                "required": ["Other"],
+                "additionalProperties": false
+            }
+        ]
+    })
+}
+
 impl From<DirectionBinding> for InputBinding {
     fn from(value: DirectionBinding) -> Self {
         match value {
@@ -383,6 +426,19 @@ impl DirectionBinding {
         }
     }
 
+    /// Button bindings of the four directions, empty for a joystick.
+    pub fn buttons(&self) -> Vec<&ButtonBinding> {
+        match self {
+            DirectionBinding::Button {
+                up,
+                down,
+                left,
+                right,
+            } => vec![up, down, left, right],
+            DirectionBinding::JoyStick { .. } => vec![],
+        }
+    }
+
     pub fn to_string_vec(&self) -> Vec<String> {
         match self {
             DirectionBinding::Button {
@@ -0,0 +1,451 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Read, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    config::{DeviceProfile, LocalConfig},
+    mask::mapping::{
+        config::{
+            MappingConfig, MappingType, parse_mapping_value, read_mapping_config,
+            save_mapping_config,
+        },
+        history::save_revision,
+        utils::Size,
+    },
+    scrcpy::app_watcher::{AppMappingRule, AppMappingRules},
+    utils::{is_safe_file_name, relate_to_data_path},
+};
+
+/// Version of the bundle layout, bundles of a newer version are rejected.
+pub const BUNDLE_VERSION: u32 = 1;
+/// Limit of the uncompressed size of each entry in a bundle.
+pub const MAX_BUNDLE_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const MAPPING_DIR: &str = "mappings/";
+const PREVIEW_DIR: &str = "previews/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub bundle_version: u32,
+    pub name: String,
+    // milliseconds since unix epoch
+    pub created: u64,
+    pub mappings: Vec<BundleMapping>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleMapping {
+    pub file: String,
+    /// resolution the mapping is made for
+    pub original_size: Size,
+    /// target packages, activating the mapping when they are in the foreground
+    #[serde(default)]
+    pub app_rules: Vec<AppMappingRule>,
+    /// devices whose profile activates the mapping
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// entry name of the preview screenshot
+    #[serde(default)]
+    pub preview: Option<String>,
+}
+
+/// What to do when a mapping file of the bundle already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum ConflictStrategy {
+    /// import as `<name> (n).json`
+    #[default]
+    Rename,
+    /// replace the existing file, which is kept as a revision
+    Overwrite,
+    /// keep the existing file
+    Skip,
+}
+
+/// Content of a bundle, read into memory before anything is written.
+pub struct Bundle {
+    pub manifest: BundleManifest,
+    pub configs: Vec<MappingConfig>,
+    pub previews: Vec<Option<Vec<u8>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedMapping {
+    pub file: String,
+    /// name of the saved file, `None` if skipped
+    pub saved_as: Option<String>,
+}
+
+/// Settings a bundle asks to change, referring to the imported file names.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleRules {
+    #[serde(default)]
+    pub app_rules: Vec<AppMappingRule>,
+    /// mapping file activated by the profile of each device, by device id
+    #[serde(default)]
+    pub device_mappings: HashMap<String, String>,
+}
+
+impl BundleRules {
+    /// Device profiles as they are after applying the rules, only those that change.
+    pub fn device_profiles(&self) -> HashMap<String, DeviceProfile> {
+        self.device_mappings
+            .iter()
+            .filter_map(|(device, file)| {
+                let mut profile = LocalConfig::get_device_profile(device).unwrap_or_default();
+                if profile.active_mapping_file.as_ref() == Some(file) {
+                    return None;
+                }
+                profile.active_mapping_file = Some(file.clone());
+                Some((device.clone(), profile))
+            })
+            .collect()
+    }
+
+    /// Add the app rules and change the device profiles, returns the changed profiles.
+    pub fn apply(&self) -> Result<HashMap<String, DeviceProfile>, String> {
+        if !self.app_rules.is_empty() {
+            let rules_existed = AppMappingRules::exists();
+            let mut app_rules = AppMappingRules::load()?;
+            // a missing rules file is loaded as disabled, an existing one keeps the choice of the user
+            if !rules_existed {
+                app_rules.enable = true;
+            }
+            for rule in self.app_rules.iter() {
+                // a rule of the same app is replaced
+                app_rules
+                    .rules
+                    .retain(|r| r.package != rule.package || r.activity != rule.activity);
+                app_rules.rules.push(rule.clone());
+            }
+            app_rules.save()?;
+        }
+
+        let device_profiles = self.device_profiles();
+        for (device, profile) in device_profiles.iter() {
+            LocalConfig::set_device_profile(device.clone(), profile.clone());
+        }
+        Ok(device_profiles)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledBundle {
+    pub mappings: Vec<ImportedMapping>,
+    /// rules of the imported mappings, which are only applied on request
+    pub rules: BundleRules,
+    pub rules_applied: bool,
+    /// device profiles that change by the rules, by device id
+    pub device_profiles: HashMap<String, DeviceProfile>,
+}
+
+/// Preview screenshot of a mapping file, stored in `mapping_preview` of the data directory.
+pub fn preview_path(file_name: &str) -> PathBuf {
+    relate_to_data_path(["mapping_preview", &format!("{}.png", file_stem(file_name))])
+}
+
+fn file_stem(file_name: &str) -> &str {
+    file_name.strip_suffix(".json").unwrap_or(file_name)
+}
+
+fn write_entry(
+    writer: &mut ZipWriter<Cursor<Vec<u8>>>,
+    entry: &str,
+    data: &[u8],
+) -> Result<(), String> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    writer
+        .start_file(entry, options)
+        .and_then(|_| writer.write_all(data).map_err(Into::into))
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotWriteBundle"), e))
+}
+
+fn bundle_error(e: impl ToString) -> String {
+    format!("{}: {}", t!("mask.mapping.invalidBundle"), e.to_string())
+}
+
+/// Pack mapping files with their rules into a zip archive.
+/// `preview` is used for the mappings without a stored preview.
+pub fn export_bundle(
+    name: &str,
+    files: &[String],
+    preview: Option<Vec<u8>>,
+) -> Result<Vec<u8>, String> {
+    let app_rules = AppMappingRules::load()?;
+    let local_config = LocalConfig::get();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::<u8>::new()));
+
+    let mut mappings = Vec::<BundleMapping>::new();
+    for file in files {
+        if !is_safe_file_name(file) {
+            return Err(format!("{}: {}", t!("mask.mapping.fileNameNotSafe"), file));
+        }
+        let (config, _) = read_mapping_config(&relate_to_data_path(["mapping", file]))?;
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| format!("{}: {}", t!("web.mapping.cannotDeserializeConfig"), e))?;
+        write_entry(
+            &mut writer,
+            &format!("{}{}", MAPPING_DIR, file),
+            content.as_bytes(),
+        )?;
+
+        let preview_data = fs::read(preview_path(file))
+            .ok()
+            .or_else(|| preview.clone());
+        let preview = match preview_data {
+            Some(data) => {
+                let entry = format!("{}{}.png", PREVIEW_DIR, file_stem(file));
+                write_entry(&mut writer, &entry, &data)?;
+                Some(entry)
+            }
+            None => None,
+        };
+
+        let mut devices: Vec<String> = local_config
+            .device_profiles
+            .iter()
+            .filter(|(_, profile)| profile.active_mapping_file.as_ref() == Some(file))
+            .map(|(device, _)| device.clone())
+            .collect();
+        devices.sort();
+
+        mappings.push(BundleMapping {
+            file: file.clone(),
+            original_size: config.original_size,
+            app_rules: app_rules
+                .rules
+                .iter()
+                .filter(|rule| rule.mapping_file == *file)
+                .cloned()
+                .collect(),
+            devices,
+            preview,
+        });
+    }
+
+    let manifest = BundleManifest {
+        bundle_version: BUNDLE_VERSION,
+        name: name.to_string(),
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+        mappings,
+    };
+    let manifest = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotWriteBundle"), e))?;
+    write_entry(&mut writer, MANIFEST_ENTRY, manifest.as_bytes())?;
+
+    writer
+        .finish()
+        .map(|cursor| cursor.into_inner())
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotWriteBundle"), e))
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>, String> {
+    let entry = archive.by_name(name).map_err(bundle_error)?;
+    if entry.size() > MAX_BUNDLE_ENTRY_SIZE {
+        return Err(bundle_error(format!(
+            "{} {}",
+            t!("mask.mapping.bundleEntryTooLarge"),
+            name
+        )));
+    }
+    // the declared size can't be trusted
+    let mut data = Vec::<u8>::new();
+    entry
+        .take(MAX_BUNDLE_ENTRY_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(bundle_error)?;
+    if data.len() as u64 > MAX_BUNDLE_ENTRY_SIZE {
+        return Err(bundle_error(format!(
+            "{} {}",
+            t!("mask.mapping.bundleEntryTooLarge"),
+            name
+        )));
+    }
+    Ok(data)
+}
+
+/// Read and check a bundle without writing anything.
+/// Mapping configs of an older schema are upgraded in memory.
+pub fn read_bundle(data: &[u8]) -> Result<Bundle, String> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(bundle_error)?;
+    let manifest: BundleManifest =
+        serde_json::from_slice(&read_entry(&mut archive, MANIFEST_ENTRY)?).map_err(bundle_error)?;
+    if manifest.bundle_version > BUNDLE_VERSION {
+        return Err(t!(
+            "mask.mapping.bundleVersionUnsupported",
+            version => manifest.bundle_version,
+            max => BUNDLE_VERSION
+        )
+        .to_string());
+    }
+
+    let mut configs = Vec::<MappingConfig>::new();
+    let mut previews = Vec::<Option<Vec<u8>>>::new();
+    for (i, mapping) in manifest.mappings.iter().enumerate() {
+        if !mapping.file.ends_with(".json") || !is_safe_file_name(&mapping.file) {
+            return Err(format!(
+                "{}: {}",
+                t!("mask.mapping.fileNameNotSafe"),
+                mapping.file
+            ));
+        }
+        if manifest.mappings[..i]
+            .iter()
+            .any(|m| m.file == mapping.file)
+        {
+            return Err(bundle_error(format!(
+                "{} {}",
+                t!("mask.mapping.bundleFileDuplicated"),
+                mapping.file
+            )));
+        }
+
+        let content = read_entry(&mut archive, &format!("{}{}", MAPPING_DIR, mapping.file))?;
+        let value: Value = serde_json::from_slice(&content)
+            .map_err(|e| format!("{}: {}", t!("web.mapping.cannotDeserializeConfig"), e))?;
+        let (config, _) = parse_mapping_value(value)?;
+        configs.push(config);
+
+        // only previews of the bundle layout are read, the manifest can't point elsewhere
+        let preview = match &mapping.preview {
+            Some(entry)
+                if entry
+                    .strip_prefix(PREVIEW_DIR)
+                    .is_some_and(is_safe_file_name) =>
+            {
+                Some(read_entry(&mut archive, entry)?)
+            }
+            _ => None,
+        };
+        previews.push(preview);
+    }
+
+    Ok(Bundle {
+        manifest,
+        configs,
+        previews,
+    })
+}
+
+fn free_file_name(file: &str, taken: &[String]) -> String {
+    (1..)
+        .map(|n| format!("{} ({}).json", file_stem(file), n))
+        .find(|name| !taken.contains(name) && !relate_to_data_path(["mapping", name]).exists())
+        .unwrap()
+}
+
+/// Save the mappings of a bundle, and apply their rules only if `apply_rules` is set.
+/// Otherwise the rules are returned, to be confirmed by the user and applied later.
+/// References between the mappings follow the renamed files.
+pub fn install_bundle(
+    mut bundle: Bundle,
+    conflict: ConflictStrategy,
+    apply_rules: bool,
+) -> Result<InstalledBundle, String> {
+    // decide the names first, so that references can be renamed before saving
+    let mut renamed = HashMap::<String, String>::new();
+    let mut taken = Vec::<String>::new();
+    let mut imported = Vec::<ImportedMapping>::new();
+    for mapping in bundle.manifest.mappings.iter() {
+        let exists = taken.contains(&mapping.file)
+            || relate_to_data_path(["mapping", &mapping.file]).exists();
+        let saved_as = match (exists, conflict) {
+            (false, _) | (true, ConflictStrategy::Overwrite) => Some(mapping.file.clone()),
+            (true, ConflictStrategy::Rename) => Some(free_file_name(&mapping.file, &taken)),
+            (true, ConflictStrategy::Skip) => None,
+        };
+        if let Some(saved_as) = &saved_as {
+            taken.push(saved_as.clone());
+            renamed.insert(mapping.file.clone(), saved_as.clone());
+        }
+        imported.push(ImportedMapping {
+            file: mapping.file.clone(),
+            saved_as,
+        });
+    }
+
+    for (config, item) in bundle.configs.iter_mut().zip(imported.iter()) {
+        if item.saved_as.is_none() {
+            continue;
+        }
+        for mapping in config.mappings.iter_mut().chain(
+            config
+                .layers
+                .iter_mut()
+                .flat_map(|layer| layer.mappings.iter_mut()),
+        ) {
+            if let MappingType::SwitchMapping(m) = mapping {
+                for file in m.files.iter_mut() {
+                    if let Some(new_file) = renamed.get(file) {
+                        *file = new_file.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    for ((config, preview), item) in bundle
+        .configs
+        .iter()
+        .zip(bundle.previews.into_iter())
+        .zip(imported.iter())
+    {
+        let Some(saved_as) = &item.saved_as else {
+            continue;
+        };
+        // an overwritten file can be restored from its revisions
+        save_revision(saved_as)?;
+        save_mapping_config(config, &relate_to_data_path(["mapping", saved_as]))?;
+        if let Some(preview) = preview {
+            let path = preview_path(saved_as);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).ok();
+            }
+            fs::write(path, preview)
+                .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotWriteBundle"), e))?;
+        }
+    }
+
+    let mut rules = BundleRules::default();
+    for (mapping, item) in bundle.manifest.mappings.iter().zip(imported.iter()) {
+        let Some(saved_as) = &item.saved_as else {
+            continue;
+        };
+        rules
+            .app_rules
+            .extend(mapping.app_rules.iter().map(|rule| AppMappingRule {
+                mapping_file: saved_as.clone(),
+                ..rule.clone()
+            }));
+        for device in mapping.devices.iter() {
+            rules
+                .device_mappings
+                .insert(device.clone(), saved_as.clone());
+        }
+    }
+
+    let device_profiles = if apply_rules {
+        rules.apply()?
+    } else {
+        rules.device_profiles()
+    };
+    Ok(InstalledBundle {
+        mappings: imported,
+        rules,
+        rules_applied: apply_rules,
+        device_profiles,
+    })
+}
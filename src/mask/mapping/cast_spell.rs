@@ -9,6 +9,7 @@ use bevy::{
 };
 use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding, PulseBinding};
 use bevy_tokio_tasks::TokioTasksRuntime;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
@@ -34,6 +35,34 @@ pub fn cast_spell_init(mut commands: Commands) {
 #[derive(Resource, Default)]
 pub struct ActiveCastSpell(Option<ActiveCastSpellItem>);
 
+impl ActiveCastSpell {
+    /// Touch up the cast if it belongs to `key`.
+    pub fn release(
+        &mut self,
+        key: &str,
+        cs_tx: &ChannelSenderCS,
+        mask_size: Vec2,
+        block_direction_pad: &mut BlockDirectionPad,
+    ) {
+        if self.0.as_ref().is_none_or(|cast| cast.key != key) {
+            return;
+        }
+        // clear and touch up
+        if let Some(cast) = self.0.take() {
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Up,
+                cast.pointer_id,
+                mask_size,
+                cast.current_pos,
+            );
+            if cast.block_direction_pad {
+                block_direction_pad.0 = false;
+            }
+        }
+    }
+}
+
 const CAST_SPELL_DELAY: u64 = 50;
 
 struct ActiveCastSpellItem {
@@ -124,7 +153,7 @@ impl ActiveCastSpellItem {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum MouseCastReleaseMode {
     OnPress,
     OnRelease,
@@ -166,7 +195,7 @@ impl From<MappingMouseCastSpell> for BindMappingMouseCastSpell {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingMouseCastSpell {
     pub note: String,
     pub pointer_id: u64,
@@ -271,185 +300,167 @@ pub fn handle_mouse_cast_spell(
     mut block_direction_pad: ResMut<BlockDirectionPad>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("MouseCastSpell") {
-                let mapping = mapping.as_ref_mousecastspell();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    let cur_cursor_pos = cursor_pos.0;
-                    let cur_mask_size = mask_size.0;
-
-                    // clear and touch up existing active cast
-                    // for OnSecondPress cast, we do the same thing
-                    if let Some(cast) = active_cast.0.take() {
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Up,
-                            cast.pointer_id,
-                            cur_mask_size,
-                            cast.current_pos,
-                        );
-
-                        if cast.block_direction_pad {
-                            block_direction_pad.0 = false;
-                        }
+        for (action, mapping) in &active_mapping.index.mouse_cast_spell {
+            if ineffable.just_activated(action.ineff_continuous()) {
+                let cur_cursor_pos = cursor_pos.0;
+                let cur_mask_size = mask_size.0;
+
+                // clear and touch up existing active cast
+                // for OnSecondPress cast, we do the same thing
+                if let Some(cast) = active_cast.0.take() {
+                    ControlMsgHelper::send_touch(
+                        &cs_tx_res.0,
+                        MotionEventAction::Up,
+                        cast.pointer_id,
+                        cur_mask_size,
+                        cast.current_pos,
+                    );
 
-                        if cast.key == action.as_ref() {
-                            continue;
-                        }
+                    if cast.block_direction_pad {
+                        block_direction_pad.0 = false;
                     }
 
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let pointer_id = mapping.pointer_id;
-                    let original_pos: Vec2 = mapping.position.into();
-                    let center_pos: Vec2 = mapping.center.into();
-                    let release_mode = mapping.release_mode.clone();
-                    let cast_no_direction = mapping.cast_no_direction;
-                    let cast_radius = mapping.cast_radius;
-                    let drag_radius = mapping.drag_radius;
-                    let horizontal_scale_factor = mapping.horizontal_scale_factor;
-                    let vertical_scale_factor = mapping.vertical_scale_factor;
-                    let mut current_pos = original_pos / original_size * cur_mask_size;
-
-                    if !matches!(mapping.release_mode, MouseCastReleaseMode::OnPress) {
-                        // set active
-                        let enable_instant =
-                            Instant::now() + Duration::from_millis(CAST_SPELL_DELAY * 2);
-
-                        let record_current_pos = if !cast_no_direction {
-                            cal_mouse_cast_spell_current_pos(
-                                cur_cursor_pos,
-                                center_pos,
-                                original_pos,
-                                cast_radius,
-                                drag_radius,
-                                cur_mask_size,
-                                original_size,
-                                horizontal_scale_factor,
-                                vertical_scale_factor,
-                            )
-                        } else {
-                            current_pos
-                        };
-
-                        active_cast.0 = Some(ActiveCastSpellItem::new_mouse_item(
-                            action.to_string(),
-                            pointer_id,
-                            record_current_pos,
-                            original_size,
-                            original_pos,
-                            mapping.drag_radius,
-                            enable_instant,
-                            center_pos,
-                            mapping.cast_radius,
-                            mapping.horizontal_scale_factor,
-                            mapping.vertical_scale_factor,
-                            mapping.cast_no_direction,
-                        ))
+                    if cast.key == action.as_ref() {
+                        continue;
                     }
+                }
 
-                    // touch down new cast
-                    ControlMsgHelper::send_touch(
-                        &cs_tx_res.0,
-                        MotionEventAction::Down,
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let pointer_id = mapping.pointer_id;
+                let original_pos: Vec2 = mapping.position.into();
+                let center_pos: Vec2 = mapping.center.into();
+                let release_mode = mapping.release_mode.clone();
+                let cast_no_direction = mapping.cast_no_direction;
+                let cast_radius = mapping.cast_radius;
+                let drag_radius = mapping.drag_radius;
+                let horizontal_scale_factor = mapping.horizontal_scale_factor;
+                let vertical_scale_factor = mapping.vertical_scale_factor;
+                let mut current_pos = original_pos / original_size * cur_mask_size;
+
+                if !matches!(mapping.release_mode, MouseCastReleaseMode::OnPress) {
+                    // set active
+                    let enable_instant =
+                        Instant::now() + Duration::from_millis(CAST_SPELL_DELAY * 2);
+
+                    let record_current_pos = if !cast_no_direction {
+                        cal_mouse_cast_spell_current_pos(
+                            cur_cursor_pos,
+                            center_pos,
+                            original_pos,
+                            cast_radius,
+                            drag_radius,
+                            cur_mask_size,
+                            original_size,
+                            horizontal_scale_factor,
+                            vertical_scale_factor,
+                        )
+                    } else {
+                        current_pos
+                    };
+
+                    active_cast.0 = Some(ActiveCastSpellItem::new_mouse_item(
+                        action.to_string(),
                         pointer_id,
-                        mask_size.0,
-                        current_pos,
-                    );
+                        record_current_pos,
+                        original_size,
+                        original_pos,
+                        mapping.drag_radius,
+                        enable_instant,
+                        center_pos,
+                        mapping.cast_radius,
+                        mapping.horizontal_scale_factor,
+                        mapping.vertical_scale_factor,
+                        mapping.cast_no_direction,
+                    ))
+                }
 
-                    let cs_tx = cs_tx_res.0.clone();
-                    runtime.spawn_background_task(move |_ctx| async move {
-                        // stay at the center
-                        let steps: u64 = 5;
-                        let step_interval = CAST_SPELL_DELAY / steps;
-                        let mut delta = Vec2::new(0., 0.);
-                        for _ in 0..steps {
+                // touch down new cast
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    mask_size.0,
+                    current_pos,
+                );
+
+                let cs_tx = cs_tx_res.0.clone();
+                runtime.spawn_background_task(move |_ctx| async move {
+                    // stay at the center
+                    let steps: u64 = 5;
+                    let step_interval = CAST_SPELL_DELAY / steps;
+                    let mut delta = Vec2::new(0., 0.);
+                    for _ in 0..steps {
+                        ControlMsgHelper::send_touch(
+                            &cs_tx,
+                            MotionEventAction::Move,
+                            pointer_id,
+                            cur_mask_size,
+                            current_pos + delta,
+                        );
+                        delta += Vec2::new(1., -1.);
+                        sleep(Duration::from_millis(step_interval)).await;
+                    }
+
+                    if !cast_no_direction {
+                        // move to direction
+                        let new_pos = cal_mouse_cast_spell_current_pos(
+                            cur_cursor_pos,
+                            center_pos,
+                            original_pos,
+                            cast_radius,
+                            drag_radius,
+                            cur_mask_size,
+                            original_size,
+                            horizontal_scale_factor,
+                            vertical_scale_factor,
+                        );
+                        let delta = new_pos - current_pos;
+                        let steps = std::cmp::max(
+                            2, // at least 2 steps
+                            (delta.length() / MIN_MOVE_STEP_LENGTH).ceil() as i32,
+                        );
+                        for step in 1..=steps {
+                            let linear_t = step as f32 / steps as f32;
+                            let interp = current_pos + delta * linear_t;
                             ControlMsgHelper::send_touch(
                                 &cs_tx,
                                 MotionEventAction::Move,
                                 pointer_id,
                                 cur_mask_size,
-                                current_pos + delta,
+                                interp,
                             );
-                            delta += Vec2::new(1., -1.);
-                            sleep(Duration::from_millis(step_interval)).await;
                         }
+                        current_pos = new_pos;
+                    }
 
-                        if !cast_no_direction {
-                            // move to direction
-                            let new_pos = cal_mouse_cast_spell_current_pos(
-                                cur_cursor_pos,
-                                center_pos,
-                                original_pos,
-                                cast_radius,
-                                drag_radius,
-                                cur_mask_size,
-                                original_size,
-                                horizontal_scale_factor,
-                                vertical_scale_factor,
-                            );
-                            let delta = new_pos - current_pos;
-                            let steps = std::cmp::max(
-                                2, // at least 2 steps
-                                (delta.length() / MIN_MOVE_STEP_LENGTH).ceil() as i32,
-                            );
-                            for step in 1..=steps {
-                                let linear_t = step as f32 / steps as f32;
-                                let interp = current_pos + delta * linear_t;
-                                ControlMsgHelper::send_touch(
-                                    &cs_tx,
-                                    MotionEventAction::Move,
-                                    pointer_id,
-                                    cur_mask_size,
-                                    interp,
-                                );
-                            }
-                            current_pos = new_pos;
-                        }
-
-                        // for OnPress cast, touch up here
-                        if matches!(release_mode, MouseCastReleaseMode::OnPress) {
-                            sleep(Duration::from_millis(CAST_SPELL_DELAY)).await;
-
-                            ControlMsgHelper::send_touch(
-                                &cs_tx,
-                                MotionEventAction::Up,
-                                pointer_id,
-                                cur_mask_size,
-                                current_pos,
-                            );
-                        }
-                    });
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    if let MouseCastReleaseMode::OnRelease = mapping.release_mode {
-                        let Some(cast) = &active_cast.0 else {
-                            continue;
-                        };
-
-                        if cast.key != action.as_ref() {
-                            continue;
-                        }
-                        // clear and touch up
-                        if let Some(cast) = active_cast.0.take() {
-                            ControlMsgHelper::send_touch(
-                                &cs_tx_res.0,
-                                MotionEventAction::Up,
-                                cast.pointer_id,
-                                mask_size.0,
-                                cast.current_pos,
-                            );
+                    // for OnPress cast, touch up here
+                    if matches!(release_mode, MouseCastReleaseMode::OnPress) {
+                        sleep(Duration::from_millis(CAST_SPELL_DELAY)).await;
 
-                            if cast.block_direction_pad {
-                                block_direction_pad.0 = false;
-                            }
-                        }
+                        ControlMsgHelper::send_touch(
+                            &cs_tx,
+                            MotionEventAction::Up,
+                            pointer_id,
+                            cur_mask_size,
+                            current_pos,
+                        );
                     }
+                });
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                if let MouseCastReleaseMode::OnRelease = mapping.release_mode {
+                    active_cast.release(
+                        action.as_ref(),
+                        &cs_tx_res,
+                        mask_size.0,
+                        &mut block_direction_pad,
+                    );
                 }
             }
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub enum PadCastReleaseMode {
     OnRelease,
     OnSecondPress,
@@ -479,7 +490,7 @@ impl From<MappingPadCastSpell> for BindMappingPadCastSpell {
             release_mode: value.release_mode,
             drag_radius: value.drag_radius,
             block_direction_pad: value.block_direction_pad,
-            pad_action: MappingAction::PadCastDirection1, // temp value
+            pad_action: MappingAction::default(), // temp value
             pad_bind: value.pad_bind.clone(),
             pad_input_binding: value.pad_bind.into(),
             bind: value.bind.clone(),
@@ -488,7 +499,7 @@ impl From<MappingPadCastSpell> for BindMappingPadCastSpell {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingPadCastSpell {
     pub note: String,
     pub pointer_id: u64,
@@ -555,108 +566,91 @@ pub fn handle_pad_cast_spell(
     mut block_direction_pad: ResMut<BlockDirectionPad>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("PadCastSpell") {
-                let mapping = mapping.as_ref_padcastspell();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    // clear and touch up existing active cast
-                    // for OnSecondPress cast, we do the same thing
-                    if let Some(cast) = active_cast.0.take() {
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Up,
-                            cast.pointer_id,
-                            mask_size.0,
-                            cast.current_pos,
-                        );
-
-                        if cast.block_direction_pad {
-                            block_direction_pad.0 = false;
-                        }
+        for (action, mapping) in &active_mapping.index.pad_cast_spell {
+            if ineffable.just_activated(action.ineff_continuous()) {
+                // clear and touch up existing active cast
+                // for OnSecondPress cast, we do the same thing
+                if let Some(cast) = active_cast.0.take() {
+                    ControlMsgHelper::send_touch(
+                        &cs_tx_res.0,
+                        MotionEventAction::Up,
+                        cast.pointer_id,
+                        mask_size.0,
+                        cast.current_pos,
+                    );
 
-                        if cast.key == action.as_ref() {
-                            continue;
-                        }
+                    if cast.block_direction_pad {
+                        block_direction_pad.0 = false;
                     }
 
-                    if mapping.block_direction_pad {
-                        // block direction pad
-                        block_direction_pad.0 = true;
-                        // touch up and remove state
-                        for (_key, item) in direction_pad_map.0.drain() {
-                            ControlMsgHelper::send_touch(
-                                &cs_tx_res.0,
-                                MotionEventAction::Up,
-                                item.pointer_id,
-                                item.original_size,
-                                item.original_pos + item.last_state,
-                            );
-                        }
+                    if cast.key == action.as_ref() {
+                        continue;
                     }
+                }
 
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let pointer_id = mapping.pointer_id;
-                    let original_pos: Vec2 = mapping.position.into();
-                    let current_pos = original_pos / original_size * mask_size.0;
-                    let enable_instant = Instant::now() + Duration::from_millis(CAST_SPELL_DELAY);
-
-                    // set active
-                    active_cast.0 = Some(ActiveCastSpellItem::new_pad_item(
-                        action.to_string(),
-                        pointer_id,
-                        current_pos,
-                        original_size,
-                        original_pos,
-                        mapping.drag_radius,
-                        enable_instant,
-                        mapping.block_direction_pad,
-                        mapping.pad_action.clone(),
-                    ));
+                if mapping.block_direction_pad {
+                    // block direction pad
+                    block_direction_pad.0 = true;
+                    // touch up and remove state
+                    for (_key, item) in direction_pad_map.0.drain() {
+                        ControlMsgHelper::send_touch(
+                            &cs_tx_res.0,
+                            MotionEventAction::Up,
+                            item.pointer_id,
+                            item.original_size,
+                            item.original_pos + item.last_state,
+                        );
+                    }
+                }
 
-                    // touch down new cast
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let pointer_id = mapping.pointer_id;
+                let original_pos: Vec2 = mapping.position.into();
+                let current_pos = original_pos / original_size * mask_size.0;
+                let enable_instant = Instant::now() + Duration::from_millis(CAST_SPELL_DELAY);
+
+                // set active
+                active_cast.0 = Some(ActiveCastSpellItem::new_pad_item(
+                    action.to_string(),
+                    pointer_id,
+                    current_pos,
+                    original_size,
+                    original_pos,
+                    mapping.drag_radius,
+                    enable_instant,
+                    mapping.block_direction_pad,
+                    mapping.pad_action,
+                ));
+
+                // touch down new cast
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    mask_size.0,
+                    current_pos,
+                );
+                // touch move around current_pos
+                let steps: u64 = 5;
+                let mut delta = Vec2::new(0., 0.);
+                for _ in 0..steps {
                     ControlMsgHelper::send_touch(
                         &cs_tx_res.0,
-                        MotionEventAction::Down,
+                        MotionEventAction::Move,
                         pointer_id,
                         mask_size.0,
-                        current_pos,
+                        current_pos + delta,
+                    );
+                    delta += Vec2::new(1., -1.);
+                }
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                if let PadCastReleaseMode::OnRelease = mapping.release_mode {
+                    active_cast.release(
+                        action.as_ref(),
+                        &cs_tx_res,
+                        mask_size.0,
+                        &mut block_direction_pad,
                     );
-                    // touch move around current_pos
-                    let steps: u64 = 5;
-                    let mut delta = Vec2::new(0., 0.);
-                    for _ in 0..steps {
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Move,
-                            pointer_id,
-                            mask_size.0,
-                            current_pos + delta,
-                        );
-                        delta += Vec2::new(1., -1.);
-                    }
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    if let PadCastReleaseMode::OnRelease = mapping.release_mode {
-                        let Some(cast) = &active_cast.0 else {
-                            continue;
-                        };
-
-                        if cast.key != action.as_ref() {
-                            continue;
-                        }
-                        // clear and touch up
-                        if let Some(cast) = active_cast.0.take() {
-                            ControlMsgHelper::send_touch(
-                                &cs_tx_res.0,
-                                MotionEventAction::Up,
-                                cast.pointer_id,
-                                mask_size.0,
-                                cast.current_pos,
-                            );
-                            if cast.block_direction_pad {
-                                block_direction_pad.0 = false;
-                            }
-                        }
-                    }
                 }
             }
         }
@@ -682,7 +676,7 @@ impl From<MappingCancelCast> for BindMappingCancelCast {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingCancelCast {
     pub note: String,
     pub position: Position,
@@ -700,81 +694,78 @@ pub fn handle_cancel_cast(
     mut active_cast: ResMut<ActiveCastSpell>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("CancelCast") {
-                let mapping = mapping.as_ref_cancelcast();
-                if ineffable.just_pulsed(action.ineff_pulse()) {
-                    // clear
-                    if let Some(cast) = active_cast.0.take() {
-                        let original_size: Vec2 = active_mapping.original_size.into();
-                        let mut cancel_pos: Vec2 = mapping.position.into();
-                        let cur_mask_size = mask_size.0;
-                        let current_pos = cast.current_pos;
-
-                        cancel_pos = cancel_pos / original_size * cur_mask_size; // relative to mask
-                        let delta = cancel_pos - current_pos;
-                        let steps = std::cmp::min(
-                            5, // at most 5 steps
-                            (delta.length() / MIN_MOVE_STEP_LENGTH).ceil() as i32,
-                        );
-                        let cs_tx = cs_tx_res.0.clone();
-                        let pointer_id = cast.pointer_id;
-                        let cast_block_direction_pad = cast.block_direction_pad;
-                        let cast_enable_instant = cast.enable_instant;
-                        runtime.spawn_background_task(move |mut ctx| async move {
-                            let now = Instant::now();
-                            if cast_enable_instant > now {
-                                sleep(cast_enable_instant - now).await;
-                            }
-
-                            let mut end_pos = current_pos;
-
-                            for step in 1..=steps {
-                                let linear_t = step as f32 / steps as f32;
-                                let interp = current_pos + delta * linear_t;
-                                ControlMsgHelper::send_touch(
-                                    &cs_tx,
-                                    MotionEventAction::Move,
-                                    pointer_id,
-                                    cur_mask_size,
-                                    interp,
-                                );
-                                end_pos = interp;
-                            }
-
-                            // stay at the end
-                            let steps: u64 = 10;
-                            let step_interval = CAST_SPELL_DELAY / steps;
-                            for _ in 0..steps {
-                                end_pos.x += 5.;
-                                ControlMsgHelper::send_touch(
-                                    &cs_tx,
-                                    MotionEventAction::Move,
-                                    pointer_id,
-                                    cur_mask_size,
-                                    end_pos,
-                                );
-                                sleep(Duration::from_millis(step_interval)).await;
-                            }
+        for (action, mapping) in &active_mapping.index.cancel_cast {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                // clear
+                if let Some(cast) = active_cast.0.take() {
+                    let original_size: Vec2 = active_mapping.original_size.into();
+                    let mut cancel_pos: Vec2 = mapping.position.into();
+                    let cur_mask_size = mask_size.0;
+                    let current_pos = cast.current_pos;
+
+                    cancel_pos = cancel_pos / original_size * cur_mask_size; // relative to mask
+                    let delta = cancel_pos - current_pos;
+                    let steps = std::cmp::min(
+                        5, // at most 5 steps
+                        (delta.length() / MIN_MOVE_STEP_LENGTH).ceil() as i32,
+                    );
+                    let cs_tx = cs_tx_res.0.clone();
+                    let pointer_id = cast.pointer_id;
+                    let cast_block_direction_pad = cast.block_direction_pad;
+                    let cast_enable_instant = cast.enable_instant;
+                    runtime.spawn_background_task(move |mut ctx| async move {
+                        let now = Instant::now();
+                        if cast_enable_instant > now {
+                            sleep(cast_enable_instant - now).await;
+                        }
+
+                        let mut end_pos = current_pos;
+
+                        for step in 1..=steps {
+                            let linear_t = step as f32 / steps as f32;
+                            let interp = current_pos + delta * linear_t;
+                            ControlMsgHelper::send_touch(
+                                &cs_tx,
+                                MotionEventAction::Move,
+                                pointer_id,
+                                cur_mask_size,
+                                interp,
+                            );
+                            end_pos = interp;
+                        }
 
+                        // stay at the end
+                        let steps: u64 = 10;
+                        let step_interval = CAST_SPELL_DELAY / steps;
+                        for _ in 0..steps {
+                            end_pos.x += 5.;
                             ControlMsgHelper::send_touch(
                                 &cs_tx,
-                                MotionEventAction::Up,
+                                MotionEventAction::Move,
                                 pointer_id,
                                 cur_mask_size,
-                                cancel_pos,
+                                end_pos,
                             );
+                            sleep(Duration::from_millis(step_interval)).await;
+                        }
 
-                            if cast_block_direction_pad {
-                                ctx.run_on_main_thread(move |ctx| {
-                                    let mut block_direction_pad =
-                                        ctx.world.resource_mut::<BlockDirectionPad>();
-                                    block_direction_pad.0 = false;
-                                })
-                                .await;
-                            }
-                        });
-                    }
+                        ControlMsgHelper::send_touch(
+                            &cs_tx,
+                            MotionEventAction::Up,
+                            pointer_id,
+                            cur_mask_size,
+                            cancel_pos,
+                        );
+
+                        if cast_block_direction_pad {
+                            ctx.run_on_main_thread(move |ctx| {
+                                let mut block_direction_pad =
+                                    ctx.world.resource_mut::<BlockDirectionPad>();
+                                block_direction_pad.0 = false;
+                            })
+                            .await;
+                        }
+                    });
                 }
             }
         }
@@ -0,0 +1,284 @@
+use std::fmt::{self, Display, Formatter};
+
+use rust_i18n::t;
+use serde::Serialize;
+
+use crate::mask::mapping::{
+    binding::ValidateMappingConfig,
+    config::{MappingConfig, MappingType},
+    utils::Anchor,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum IssueLevel {
+    /// the config still works, but probably not as intended
+    Warning,
+    /// the config can't be loaded
+    Error,
+}
+
+/// A problem found in a mapping config.
+/// `index` points at the mapping in the base list, or in the mappings of `layer`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingIssue {
+    pub level: IssueLevel,
+    pub layer: Option<String>,
+    pub index: Option<usize>,
+    pub mapping_type: Option<String>,
+    pub message: String,
+}
+
+impl Display for MappingIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(layer) = &self.layer {
+            write!(f, "[Layer-{}]", layer)?;
+        }
+        if let (Some(mapping_type), Some(index)) = (&self.mapping_type, self.index) {
+            write!(f, "[{}#{}]", mapping_type, index)?;
+        }
+        if self.layer.is_some() || self.index.is_some() {
+            f.write_str(" ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+struct Entry<'a> {
+    layer: Option<&'a str>,
+    index: usize,
+    mapping: &'a MappingType,
+}
+
+impl Entry<'_> {
+    // mappings of different layers are assumed not to be active together
+    fn coexists_with(&self, other: &Entry) -> bool {
+        self.layer.is_none() || other.layer.is_none() || self.layer == other.layer
+    }
+
+    fn name(&self) -> String {
+        match self.layer {
+            Some(layer) => format!("{}/{}#{}", layer, self.mapping.as_ref(), self.index),
+            None => format!("{}#{}", self.mapping.as_ref(), self.index),
+        }
+    }
+
+    fn issue(&self, level: IssueLevel, message: String) -> MappingIssue {
+        MappingIssue {
+            level,
+            layer: self.layer.map(|layer| layer.to_string()),
+            index: Some(self.index),
+            mapping_type: Some(self.mapping.as_ref().to_string()),
+            message,
+        }
+    }
+}
+
+fn layer_issue(layer: &str, message: String) -> MappingIssue {
+    MappingIssue {
+        level: IssueLevel::Error,
+        layer: Some(layer.to_string()),
+        index: None,
+        mapping_type: None,
+        message,
+    }
+}
+
+/// Check each mapping and the relations between them.
+pub fn check_mapping_config(config: &MappingConfig) -> Vec<MappingIssue> {
+    let mut issues = Vec::<MappingIssue>::new();
+
+    let mut layer_names = Vec::<&str>::new();
+    for layer in config.layers.iter() {
+        if let Err(e) = layer.validate() {
+            issues.push(layer_issue(&layer.name, e));
+        }
+        if layer_names.contains(&layer.name.as_str()) {
+            issues.push(layer_issue(
+                &layer.name,
+                t!("mask.mapping.layerNameDuplicated").to_string(),
+            ));
+        }
+        layer_names.push(&layer.name);
+    }
+
+    let entries: Vec<Entry> = config
+        .mappings
+        .iter()
+        .enumerate()
+        .map(|(index, mapping)| Entry {
+            layer: None,
+            index,
+            mapping,
+        })
+        .chain(config.layers.iter().flat_map(|layer| {
+            layer
+                .mappings
+                .iter()
+                .enumerate()
+                .map(|(index, mapping)| Entry {
+                    layer: Some(layer.name.as_str()),
+                    index,
+                    mapping,
+                })
+        }))
+        .collect();
+
+    let (width, height) = (
+        config.original_size.width as f32,
+        config.original_size.height as f32,
+    );
+    for (i, entry) in entries.iter().enumerate() {
+        if let Err(e) = entry.mapping.validate() {
+            issues.push(entry.issue(IssueLevel::Error, e));
+        }
+
+        for position in entry.mapping.positions() {
+            let outside = match position.anchor {
+                Anchor::Absolute => {
+                    position.x < 0. || position.y < 0. || position.x > width || position.y > height
+                }
+                Anchor::Relative => {
+                    !(0.0..=1.0).contains(&position.x) || !(0.0..=1.0).contains(&position.y)
+                }
+                // depends on the device size
+                _ => false,
+            };
+            if outside {
+                issues.push(
+                    entry.issue(
+                        IssueLevel::Warning,
+                        t!(
+                            "mask.mapping.positionOutOfRange",
+                            x => position.x,
+                            y => position.y,
+                            width => width,
+                            height => height
+                        )
+                        .to_string(),
+                    ),
+                );
+            }
+        }
+
+        // compare with the previous ones, so that each pair is reported once
+        for other in entries[..i].iter() {
+            if !entry.coexists_with(other) {
+                continue;
+            }
+            let other_bindings = other.mapping.bindings();
+            for binding in entry.mapping.bindings() {
+                if !binding.is_empty() && other_bindings.contains(&binding) {
+                    issues.push(
+                        entry.issue(
+                            IssueLevel::Warning,
+                            t!(
+                                "mask.mapping.bindingConflict",
+                                binding => binding,
+                                other => other.name()
+                            )
+                            .to_string(),
+                        ),
+                    );
+                }
+            }
+            let other_pointer_ids = other.mapping.pointer_ids();
+            for pointer_id in entry.mapping.pointer_ids() {
+                if other_pointer_ids.contains(&pointer_id) {
+                    issues.push(
+                        entry.issue(
+                            IssueLevel::Warning,
+                            t!(
+                                "mask.mapping.pointerIdConflict",
+                                pointer_id => pointer_id,
+                                other => other.name()
+                            )
+                            .to_string(),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::mapping::{
+        binding::ButtonBinding,
+        config::default_mapping_config,
+        layer::{LayerMode, MappingLayer},
+        tap::MappingSingleTap,
+        utils::Position,
+    };
+
+    fn tap(pointer_id: u64, key: &str) -> MappingType {
+        MappingType::SingleTap(MappingSingleTap {
+            position: Position {
+                x: 100.,
+                y: 100.,
+                anchor: Anchor::Absolute,
+            },
+            note: String::new(),
+            pointer_id,
+            duration: 50,
+            sync: false,
+            humanize: None,
+            bind: ButtonBinding::new(vec![key.parse().unwrap()]),
+        })
+    }
+
+    fn layer(name: &str, mappings: Vec<MappingType>) -> MappingLayer {
+        MappingLayer {
+            name: name.to_string(),
+            mode: LayerMode::Hold,
+            bind: ButtonBinding::new(vec!["KeyF".parse().unwrap()]),
+            mappings,
+        }
+    }
+
+    #[test]
+    fn binding_conflict() {
+        let mut config = default_mapping_config();
+        config.mappings = vec![tap(1, "KeyA"), tap(2, "KeyB"), tap(3, "KeyA")];
+        // layers are not active together, so they may reuse the keys of each other
+        config.layers = vec![
+            layer("vehicle", vec![tap(4, "KeyC")]),
+            layer("swim", vec![tap(5, "KeyC")]),
+        ];
+
+        let issues = check_mapping_config(&config);
+        assert_eq!(issues.len(), 1);
+        let issue = &issues[0];
+        assert_eq!(issue.level, IssueLevel::Warning);
+        assert_eq!((issue.layer.as_deref(), issue.index), (None, Some(2)));
+        assert!(issue.message.contains("KeyA"));
+        assert!(issue.message.contains("SingleTap#0"));
+    }
+
+    #[test]
+    fn pointer_id_conflict() {
+        let mut config = default_mapping_config();
+        config.mappings = vec![tap(1, "KeyA")];
+        config.layers = vec![
+            layer("vehicle", vec![tap(1, "KeyB"), tap(2, "KeyC")]),
+            layer("swim", vec![tap(2, "KeyD")]),
+        ];
+
+        let issues = check_mapping_config(&config);
+        assert_eq!(issues.len(), 1);
+        let issue = &issues[0];
+        assert_eq!(issue.level, IssueLevel::Warning);
+        assert_eq!(
+            (issue.layer.as_deref(), issue.index),
+            (Some("vehicle"), Some(0))
+        );
+        assert!(issue.message.contains("SingleTap#0"));
+        assert_eq!(
+            issue.to_string(),
+            format!("[Layer-vehicle][SingleTap#0] {}", issue.message)
+        );
+    }
+}
@@ -1,127 +1,242 @@
 use std::{
-    collections::HashMap,
-    fs::{File, create_dir_all},
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs::{File, copy, create_dir_all},
     io::Write,
-    path::Path,
-    str::FromStr,
+    marker::PhantomData,
+    mem,
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+    time::SystemTime,
 };
 
-use bevy::{ecs::resource::Resource, math::Vec2};
+use bevy::{
+    app::App,
+    ecs::{resource::Resource, system::RunSystemOnce, world::World},
+    log,
+    math::Vec2,
+};
 use bevy_ineffable::{
     config::InputConfig,
-    phantom::IAWrp,
-    prelude::{InputAction, InputBinding},
+    phantom::{Continuous, DualAxis, IAWrp, Pulse},
+    prelude::{IneffableCommands, InputAction, InputActionRegistrar, InputBinding, InputKind},
 };
+use once_cell::sync::Lazy;
 use paste::paste;
 use rust_i18n::t;
-use seq_macro::seq;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::to_string_pretty;
-use strum_macros::{AsRefStr, Display, EnumString};
+use serde_json::{Value, to_string_pretty};
+use strum_macros::{AsRefStr, IntoStaticStr};
 
 use crate::{
     mask::mapping::{
-        binding::ValidateMappingConfig,
+        binding::{ButtonBinding, MergedButton, ValidateMappingConfig},
         cast_spell::{
             BindMappingCancelCast, BindMappingMouseCastSpell, BindMappingPadCastSpell,
             MappingCancelCast, MappingMouseCastSpell, MappingPadCastSpell,
         },
+        check::{IssueLevel, MappingIssue, check_mapping_config},
         direction_pad::{BindMappingDirectionPad, MappingDirectionPad},
         fire::{BindMappingFire, BindMappingFps, MappingFire, MappingFps},
+        gesture::{BindMappingGesture, MappingGesture},
+        layer::{BindMappingLayer, MappingLayer},
         observation::{BindMappingObservation, MappingObservation},
         raw_input::{BindMappingRawInput, MappingRawInput},
         script::{BindMappingScript, MappingScript},
+        scroll::{BindMappingScroll, MappingScroll},
         swipe::{BindMappingSwipe, MappingSwipe},
+        switch_mapping::{BindMappingSwitchMapping, MappingSwitchMapping},
         tap::{
             BindMappingMultipleTap, BindMappingRepeatTap, BindMappingSingleTap, MappingMultipleTap,
             MappingRepeatTap, MappingSingleTap,
         },
-        utils::Size,
+        utils::{Position, Size},
+        version::{MAPPING_CONFIG_VERSION, upgrade_mapping_config},
     },
     utils::{is_safe_file_name, relate_to_data_path},
 };
 
-// declare 32 actions for each kind of key mapping
-seq!(N in 1..=32 {
-    #[derive(InputAction, Serialize, Deserialize, Debug, Hash, PartialEq, Eq, Clone, AsRefStr, Display, EnumString)]
-    pub enum MappingAction {
-        #(
-            #[ineffable(continuous)]
-            SingleTap~N,
-            #[ineffable(continuous)]
-            RepeatTap~N,
-            #[ineffable(pulse)]
-            MultipleTap~N,
-            #[ineffable(pulse)]
-            Swipe~N,
-            #[ineffable(dual_axis)]
-            DirectionPad~N,
-            #[ineffable(continuous)]
-            MouseCastSpell~N,
-            #[ineffable(continuous)]
-            PadCastSpell~N,
-            #[ineffable(dual_axis)]
-            PadCastDirection~N,
-            #[ineffable(pulse)]
-            CancelCast~N,
-            #[ineffable(continuous)]
-            Observation~N,
-            #[ineffable(pulse)]
-            Fps~N,
-            #[ineffable(continuous)]
-            Fire~N,
-            #[ineffable(pulse)]
-            RawInput~N,
-            #[ineffable(continuous)]
-            Script~N,
-        )*
-    }
-
-    impl MappingAction {
-        pub fn ineff_continuous(&self) -> IAWrp<MappingAction, bevy_ineffable::phantom::Continuous> {
-            match self {
-                #(
-                    MappingAction::SingleTap~N => self.clone()._singletap~N(),
-                    MappingAction::RepeatTap~N => self.clone()._repeattap~N(),
-                    MappingAction::MouseCastSpell~N => self.clone()._mousecastspell~N(),
-                    MappingAction::PadCastSpell~N => self.clone()._padcastspell~N(),
-                    MappingAction::Observation~N => self.clone()._observation~N(),
-                    MappingAction::Fire~N => self.clone()._fire~N(),
-                    MappingAction::Script~N => self.clone()._script~N(),
-                )*
-                _ => panic!("ineff_continuous called on non-continuous variant"),
-            }
-        }
+const SLOT_KINDS: [InputKind; 3] = [InputKind::Continuous, InputKind::Pulse, InputKind::DualAxis];
 
-        pub fn ineff_pulse(&self) -> IAWrp<MappingAction, bevy_ineffable::phantom::Pulse> {
-            match self {
-                #(
-                    MappingAction::MultipleTap~N => self.clone()._multipletap~N(),
-                    MappingAction::Swipe~N => self.clone()._swipe~N(),
-                    MappingAction::CancelCast~N => self.clone()._cancelcast~N(),
-                    MappingAction::Fps~N => self.clone()._fps~N(),
-                    MappingAction::RawInput~N => self.clone()._rawinput~N(),
-                )*
-                _ => panic!("ineff_pulse called on non-pulse variant"),
-            }
+/// Number of input slots of each input kind registered on startup.
+const INITIAL_SLOTS_PER_KIND: usize = 64;
+
+/// Input slots registered to ineffable, which only accepts the actions a group had when it was
+/// registered. A config needing more slots registers a larger pool as a new group, see
+/// `apply_mapping_input`. The slots are handed out to mappings whenever a config is loaded.
+struct SlotPool {
+    group_id: &'static str,
+    capacity: [usize; 3],
+    generation: usize,
+}
+
+static SLOT_POOL: Lazy<RwLock<SlotPool>> = Lazy::new(|| {
+    RwLock::new(SlotPool {
+        group_id: "MappingAction",
+        capacity: [INITIAL_SLOTS_PER_KIND; 3],
+        generation: 0,
+    })
+});
+
+// ids of the slots of each input kind, leaked once as ineffable wants them static
+static SLOT_IDS: Lazy<Mutex<[Vec<&'static str>; 3]>> = Lazy::new(Default::default);
+
+fn slot_id(kind_index: usize, n: usize) -> &'static str {
+    let mut ids = SLOT_IDS.lock().unwrap();
+    let ids = &mut ids[kind_index];
+    while ids.len() <= n {
+        let id = format!("{:?}{}", SLOT_KINDS[kind_index], ids.len() + 1);
+        ids.push(Box::leak(id.into_boxed_str()));
+    }
+    ids[n]
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ActionSlot {
+    kind_index: usize,
+    n: usize,
+}
+
+impl InputAction for ActionSlot {
+    fn group_id() -> &'static str {
+        SLOT_POOL.read().unwrap().group_id
+    }
+
+    fn action_id(&self) -> &'static str {
+        slot_id(self.kind_index, self.n)
+    }
+
+    fn index(&self) -> usize {
+        let capacity = SLOT_POOL.read().unwrap().capacity;
+        capacity[..self.kind_index].iter().sum::<usize>() + self.n
+    }
+
+    fn kind(&self) -> InputKind {
+        SLOT_KINDS[self.kind_index]
+    }
+
+    fn iter() -> impl Iterator<Item = Self> {
+        let capacity = SLOT_POOL.read().unwrap().capacity;
+        (0..SLOT_KINDS.len()).flat_map(move |kind_index| {
+            (0..capacity[kind_index]).map(move |n| ActionSlot { kind_index, n })
+        })
+    }
+}
+
+/// Register a larger slot pool if the active config needs more slots than registered.
+fn ensure_action_slots(world: &mut World, needed: [usize; 3]) {
+    {
+        let mut pool = SLOT_POOL.write().unwrap();
+        if needed
+            .iter()
+            .zip(pool.capacity.iter())
+            .all(|(needed, capacity)| needed <= capacity)
+        {
+            return;
         }
+        for (capacity, needed) in pool.capacity.iter_mut().zip(needed) {
+            *capacity = (*capacity).max(needed.next_power_of_two());
+        }
+        pool.generation += 1;
+        pool.group_id = Box::leak(format!("MappingAction{}", pool.generation).into_boxed_str());
+        log::info!(
+            "[Mapping] {}",
+            t!(
+                "mask.mapping.actionSlotsGrown",
+                continuous => pool.capacity[0],
+                pulse => pool.capacity[1],
+                dual_axis => pool.capacity[2]
+            )
+        );
+    }
+    // ineffable only registers groups on the app, so borrow the world for a moment
+    let mut app = App::empty();
+    mem::swap(app.world_mut(), world);
+    app.register_input_action::<ActionSlot>();
+    mem::swap(app.world_mut(), world);
+}
 
-        pub fn ineff_dual_axis(&self) -> IAWrp<MappingAction, bevy_ineffable::phantom::DualAxis> {
-            match self {
-                #(
-                    MappingAction::DirectionPad~N => self.clone()._directionpad~N(),
-                    MappingAction::PadCastDirection~N => self.clone()._padcastdirection~N(),
-                )*
-                _ => panic!("ineff_dual_axis called on non-dual_axis variant"),
-            }
+/// Bind the keys of the active mapping config, registering more input slots if it needs them.
+/// Queued as a command wherever a config is activated, as it needs the whole world.
+pub fn apply_mapping_input(world: &mut World) {
+    let Some(needed) = world
+        .resource::<ActiveMappingConfig>()
+        .0
+        .as_ref()
+        .map(|config| config.action_slots)
+    else {
+        return;
+    };
+    ensure_action_slots(world, needed);
+
+    let input_config =
+        InputConfig::from(world.resource::<ActiveMappingConfig>().0.as_ref().unwrap());
+    if let Err(e) = world.run_system_once(move |mut ineffable: IneffableCommands| {
+        ineffable.set_config(&input_config);
+    }) {
+        log::error!("[Mapping] {}", e);
+    }
+}
+
+/// Input action of a loaded mapping, allocated by `ActionAllocator`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MappingAction {
+    mapping_type: &'static str,
+    slot: ActionSlot,
+}
+
+impl MappingAction {
+    pub fn mapping_type(&self) -> &'static str {
+        self.mapping_type
+    }
+
+    pub fn ineff_continuous(&self) -> IAWrp<ActionSlot, Continuous> {
+        IAWrp(self.slot, PhantomData)
+    }
+
+    pub fn ineff_pulse(&self) -> IAWrp<ActionSlot, Pulse> {
+        IAWrp(self.slot, PhantomData)
+    }
+
+    pub fn ineff_dual_axis(&self) -> IAWrp<ActionSlot, DualAxis> {
+        IAWrp(self.slot, PhantomData)
+    }
+}
+
+// unique in a loaded config, used as key of active mapping items
+impl AsRef<str> for MappingAction {
+    fn as_ref(&self) -> &str {
+        self.slot.action_id()
+    }
+}
+
+impl Display for MappingAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.slot.action_id())
+    }
+}
+
+/// Hands out action slots of each input kind in order, counting the slots a config needs.
+#[derive(Default)]
+struct ActionAllocator([usize; 3]);
+
+impl ActionAllocator {
+    fn alloc(&mut self, mapping_type: &'static str, kind: InputKind) -> MappingAction {
+        let kind_index = SLOT_KINDS.iter().position(|k| *k == kind).unwrap();
+        let n = self.0[kind_index];
+        self.0[kind_index] += 1;
+        MappingAction {
+            mapping_type,
+            slot: ActionSlot { kind_index, n },
         }
     }
-});
+}
 
 macro_rules! impl_mapping_related {
     ( $($variant:ident),* $(,)? ) => {
         paste! {
-            #[derive(Serialize, Deserialize, Debug, Clone, AsRefStr)]
+            #[derive(Serialize, Deserialize, Debug, Clone, AsRefStr, IntoStaticStr, JsonSchema)]
             #[serde(tag = "type")]
             pub enum MappingType {
                 $(
@@ -166,17 +281,29 @@ macro_rules! impl_mapping_related {
                     )*
                 }
             }
+        }
 
-            $(
-                paste! {
-                    pub fn [<as_ref_ $variant:lower>](&self) -> & [<BindMapping $variant>] {
-                        match self {
-                            BindMappingType::$variant(inner) => inner,
-                            _ => panic!(concat!("Not a ", stringify!($variant), " mapping")),
-                        }
+        paste! {
+            /// Active mappings grouped by type, built when the config is loaded or layers change,
+            /// so that each handler only iterates over its own mappings.
+            #[derive(Debug, Clone, Default)]
+            pub struct MappingIndex {
+                $(
+                    pub [<$variant:snake>]: Vec<(MappingAction, [<BindMapping $variant>])>,
+                )*
+            }
+
+            impl MappingIndex {
+                fn push(&mut self, action: MappingAction, mapping: &BindMappingType) {
+                    match mapping {
+                        $(
+                            BindMappingType::$variant(inner) => {
+                                self.[<$variant:snake>].push((action, inner.clone()))
+                            }
+                        )*
                     }
                 }
-            )*
+            }
         }
     };
 }
@@ -186,6 +313,8 @@ impl_mapping_related! {
     RepeatTap,
     MultipleTap,
     Swipe,
+    Gesture,
+    Scroll,
     DirectionPad,
     MouseCastSpell,
     PadCastSpell,
@@ -194,55 +323,153 @@ impl_mapping_related! {
     Fps,
     Fire,
     RawInput,
-    Script
+    Script,
+    SwitchMapping
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingConfig {
     pub version: String,
     pub original_size: Size,
     pub mappings: Vec<MappingType>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<MappingLayer>,
 }
 
 #[derive(Debug, Clone)]
 pub struct BindMappingConfig {
     pub version: String,
     pub original_size: Size,
+    // mappings of the base and active layers, which handlers iterate over
     pub mappings: HashMap<MappingAction, BindMappingType>,
+    pub index: MappingIndex,
+    pub all_mappings: HashMap<MappingAction, BindMappingType>,
+    // layer name of mappings not in the base
+    pub mapping_layer: HashMap<MappingAction, String>,
+    pub layers: Vec<BindMappingLayer>,
+    pub active_layers: HashSet<String>,
+    // mappings triggered by the mouse wheel
+    pub wheel_actions: HashSet<MappingAction>,
+    // input slots used of each input kind
+    pub action_slots: [usize; 3],
 }
 
 impl From<MappingConfig> for BindMappingConfig {
+    // config must be validated before, so that there are enough action slots
     fn from(value: MappingConfig) -> Self {
         let mut mappings = HashMap::<MappingAction, BindMappingType>::new();
-        let mut mapping_type_map = HashMap::<String, u32>::new();
-        for mapping in value.mappings.into_iter() {
-            let name = mapping.as_ref();
-            let count = *mapping_type_map
-                .entry(name.to_string())
-                .and_modify(|c| *c += 1)
-                .or_insert(1);
-            let action_name = format!("{}{}", name, count);
-            let action = MappingAction::from_str(&action_name).unwrap();
+        let mut mapping_layer = HashMap::<MappingAction, String>::new();
+        let mut wheel_actions = HashSet::<MappingAction>::new();
+        let mut layers = Vec::<BindMappingLayer>::new();
+        let mut allocator = ActionAllocator::default();
+
+        let mut all = value
+            .mappings
+            .into_iter()
+            .map(|mapping| (None, mapping))
+            .collect::<Vec<_>>();
+        for layer in value.layers.into_iter() {
+            let action = allocator.alloc("Layer", InputKind::Continuous);
+            layers.push(BindMappingLayer::new(&layer, action));
+            all.extend(
+                layer
+                    .mappings
+                    .into_iter()
+                    .map(|mapping| (Some(layer.name.clone()), mapping)),
+            );
+        }
+
+        for (layer, mapping) in all.into_iter() {
+            let action = allocator.alloc((&mapping).into(), mapping.input_kind());
+            if let Some(layer) = layer {
+                mapping_layer.insert(action, layer);
+            }
+            if mapping.button_bindings().iter().any(|binding| {
+                binding.buttons().iter().any(|button| {
+                    matches!(button, MergedButton::ScrollUp | MergedButton::ScrollDown)
+                })
+            }) {
+                wheel_actions.insert(action);
+            }
 
             if let MappingType::PadCastSpell(mapping_pad_cast_spell) = mapping {
-                let pad_action_name = format!("PadCastDirection{count}");
                 let mut bind_mapping: BindMappingPadCastSpell = mapping_pad_cast_spell.into();
-                bind_mapping.pad_action = MappingAction::from_str(&pad_action_name).unwrap();
+                bind_mapping.pad_action = allocator.alloc("PadCastDirection", InputKind::DualAxis);
                 mappings.insert(action, BindMappingType::PadCastSpell(bind_mapping));
+            } else if let MappingType::DirectionPad(mapping_direction_pad) = mapping {
+                let mut bind_mapping: BindMappingDirectionPad = mapping_direction_pad.into();
+                for modifier in bind_mapping.modifiers.iter_mut() {
+                    modifier.action = allocator.alloc("PadModifier", InputKind::Continuous);
+                }
+                mappings.insert(action, BindMappingType::DirectionPad(bind_mapping));
+            } else if let MappingType::Fps(mapping_fps) = mapping {
+                let mut bind_mapping: BindMappingFps = mapping_fps.into();
+                if let Some(ads) = bind_mapping.ads.as_mut() {
+                    ads.action = allocator.alloc("FpsAds", InputKind::Continuous);
+                }
+                for preset in bind_mapping.presets.iter_mut() {
+                    preset.action = allocator.alloc("SensitivityPreset", InputKind::Pulse);
+                }
+                mappings.insert(action, BindMappingType::Fps(bind_mapping));
             } else {
                 mappings.insert(action, mapping.into());
             }
         }
 
-        Self {
+        let mut config = Self {
             version: value.version,
             original_size: value.original_size,
-            mappings,
-        }
+            mappings: HashMap::new(),
+            index: MappingIndex::default(),
+            all_mappings: mappings,
+            mapping_layer,
+            layers,
+            active_layers: HashSet::new(),
+            wheel_actions,
+            action_slots: allocator.0,
+        };
+        config.refresh_active_mappings();
+        config
     }
 }
 
 impl BindMappingConfig {
+    pub fn set_layer_active(&mut self, name: &str, active: bool) {
+        if active {
+            self.active_layers.insert(name.to_string());
+        } else {
+            self.active_layers.remove(name);
+        }
+        self.refresh_active_mappings();
+    }
+
+    /// Whether the mouse wheel triggers any of the active mappings.
+    pub fn is_wheel_bound(&self) -> bool {
+        self.wheel_actions
+            .iter()
+            .any(|action| self.mappings.contains_key(action))
+    }
+
+    fn refresh_active_mappings(&mut self) {
+        self.mappings = self
+            .all_mappings
+            .iter()
+            .filter(|(action, _)| match self.mapping_layer.get(action) {
+                Some(layer) => self.active_layers.contains(layer),
+                None => true,
+            })
+            .map(|(action, mapping)| (*action, mapping.clone()))
+            .collect();
+
+        // keep the order of the config file
+        let mut actions: Vec<&MappingAction> = self.mappings.keys().collect();
+        actions.sort_by_key(|action| action.slot.0);
+        self.index = MappingIndex::default();
+        for action in actions {
+            self.index.push(*action, &self.mappings[action]);
+        }
+    }
+
     pub fn get_mapping_label_info(&self) -> Vec<(&BindMappingType, String, Vec2, Vec2)> {
         let size: Vec2 = self.original_size.into();
         self.mappings
@@ -255,6 +482,8 @@ impl BindMappingConfig {
                         (m.bind.to_string(), m.items[0].position.into())
                     }
                     BindMappingType::Swipe(m) => (m.bind.to_string(), m.positions[0].into()),
+                    BindMappingType::Gesture(m) => (m.bind.to_string(), m.position.into()),
+                    BindMappingType::Scroll(m) => (m.bind.to_string(), m.position.into()),
                     BindMappingType::DirectionPad(m) => (String::new(), m.position.into()),
                     BindMappingType::MouseCastSpell(m) => (m.bind.to_string(), m.position.into()),
                     BindMappingType::PadCastSpell(m) => (String::new(), m.position.into()),
@@ -264,6 +493,7 @@ impl BindMappingConfig {
                     BindMappingType::Fire(m) => (m.bind.to_string(), m.position.into()),
                     BindMappingType::RawInput(m) => (m.bind.to_string(), m.position.into()),
                     BindMappingType::Script(m) => (m.bind.to_string(), m.position.into()),
+                    BindMappingType::SwitchMapping(m) => (m.bind.to_string(), m.position.into()),
                 };
                 (mapping, binding, pos, size)
             })
@@ -275,17 +505,40 @@ impl From<&BindMappingConfig> for InputConfig {
     fn from(mapping_config: &BindMappingConfig) -> Self {
         let mut all_bindings: HashMap<String, Vec<InputBinding>> = HashMap::new();
 
-        for (action, mapping) in &mapping_config.mappings {
+        // bind all layers, so that the input config is kept when layers change
+        for (action, mapping) in &mapping_config.all_mappings {
             if let BindMappingType::PadCastSpell(m) = mapping {
                 all_bindings.insert(action.to_string(), vec![m.input_binding.clone()]);
                 all_bindings.insert(m.pad_action.to_string(), vec![m.pad_input_binding.clone()]);
             } else {
                 all_bindings.insert(action.to_string(), vec![mapping.get_input_binding()]);
             }
+            if let BindMappingType::DirectionPad(m) = mapping {
+                for modifier in &m.modifiers {
+                    all_bindings.insert(
+                        modifier.action.to_string(),
+                        vec![modifier.input_binding.clone()],
+                    );
+                }
+            }
+            if let BindMappingType::Fps(m) = mapping {
+                if let Some(ads) = &m.ads {
+                    all_bindings.insert(ads.action.to_string(), vec![ads.input_binding.clone()]);
+                }
+                for preset in &m.presets {
+                    all_bindings.insert(
+                        preset.action.to_string(),
+                        vec![preset.input_binding.clone()],
+                    );
+                }
+            }
+        }
+        for layer in &mapping_config.layers {
+            all_bindings.insert(layer.action.to_string(), vec![layer.input_binding.clone()]);
         }
 
         let binding_config: HashMap<String, HashMap<String, Vec<InputBinding>>> =
-            HashMap::from([("MappingAction".to_string(), all_bindings)]);
+            HashMap::from([(ActionSlot::group_id().to_string(), all_bindings)]);
         let mut input_config = InputConfig::new();
         input_config.bindings = binding_config;
         input_config
@@ -295,46 +548,238 @@ impl From<&BindMappingConfig> for InputConfig {
 #[derive(Resource, Debug, Clone, Default)]
 pub struct ActiveMappingConfig(pub Option<BindMappingConfig>, pub String);
 
+impl MappingType {
+    pub fn input_kind(&self) -> InputKind {
+        match self {
+            MappingType::SingleTap(_)
+            | MappingType::RepeatTap(_)
+            | MappingType::MouseCastSpell(_)
+            | MappingType::PadCastSpell(_)
+            | MappingType::Observation(_)
+            | MappingType::Fire(_)
+            | MappingType::Script(_)
+            | MappingType::Scroll(_) => InputKind::Continuous,
+            MappingType::MultipleTap(_)
+            | MappingType::Swipe(_)
+            | MappingType::Gesture(_)
+            | MappingType::CancelCast(_)
+            | MappingType::Fps(_)
+            | MappingType::RawInput(_)
+            | MappingType::SwitchMapping(_) => InputKind::Pulse,
+            MappingType::DirectionPad(_) => InputKind::DualAxis,
+        }
+    }
+
+    pub fn positions(&self) -> Vec<&Position> {
+        match self {
+            MappingType::SingleTap(m) => vec![&m.position],
+            MappingType::RepeatTap(m) => vec![&m.position],
+            MappingType::MultipleTap(m) => m.items.iter().map(|i| &i.position).collect(),
+            MappingType::Swipe(m) => m
+                .positions
+                .iter()
+                .chain(m.segments.iter().flat_map(|s| s.control_points.iter()))
+                .collect(),
+            MappingType::Gesture(m) => vec![&m.position],
+            MappingType::Scroll(m) => vec![&m.position],
+            MappingType::DirectionPad(m) => vec![&m.position],
+            MappingType::MouseCastSpell(m) => vec![&m.position, &m.center],
+            MappingType::PadCastSpell(m) => vec![&m.position],
+            MappingType::CancelCast(m) => vec![&m.position],
+            MappingType::Observation(m) => vec![&m.position],
+            MappingType::Fps(m) => vec![&m.position],
+            MappingType::Fire(m) => vec![&m.position],
+            MappingType::RawInput(m) => vec![&m.position],
+            MappingType::Script(m) => vec![&m.position],
+            MappingType::SwitchMapping(m) => vec![&m.position],
+        }
+    }
+
+    /// Pointers used to touch the device, empty if the mapping doesn't hold one.
+    pub fn pointer_ids(&self) -> Vec<u64> {
+        match self {
+            MappingType::SingleTap(m) => vec![m.pointer_id],
+            MappingType::RepeatTap(m) => vec![m.pointer_id],
+            MappingType::MultipleTap(m) => vec![m.pointer_id],
+            MappingType::Swipe(m) => vec![m.pointer_id],
+            MappingType::Gesture(m) => m.pointer_ids.clone(),
+            MappingType::DirectionPad(m) => vec![m.pointer_id],
+            MappingType::MouseCastSpell(m) => vec![m.pointer_id],
+            MappingType::PadCastSpell(m) => vec![m.pointer_id],
+            MappingType::Observation(m) => vec![m.pointer_id],
+            MappingType::Fps(m) => {
+                let mut pointer_ids = vec![m.pointer_id];
+                pointer_ids.extend(m.recenter.alternate_pointer_id);
+                pointer_ids
+            }
+            MappingType::Fire(m) => vec![m.pointer_id],
+            MappingType::CancelCast(_)
+            | MappingType::Scroll(_)
+            | MappingType::RawInput(_)
+            | MappingType::Script(_)
+            | MappingType::SwitchMapping(_) => vec![],
+        }
+    }
+
+    /// Keys bound by the mapping, with the buttons of a chord sorted.
+    pub fn bindings(&self) -> Vec<String> {
+        let bindings = match self {
+            MappingType::SingleTap(m) => vec![m.bind.to_string()],
+            MappingType::RepeatTap(m) => vec![m.bind.to_string()],
+            MappingType::MultipleTap(m) => vec![m.bind.to_string()],
+            MappingType::Swipe(m) => vec![m.bind.to_string()],
+            MappingType::Gesture(m) => vec![m.bind.to_string()],
+            MappingType::Scroll(m) => vec![m.bind.to_string()],
+            MappingType::DirectionPad(m) => {
+                let mut bindings = m.bind.to_string_vec();
+                bindings.extend(m.modifiers.iter().map(|modifier| modifier.bind.to_string()));
+                bindings
+            }
+            MappingType::MouseCastSpell(m) => vec![m.bind.to_string()],
+            MappingType::PadCastSpell(m) => {
+                let mut bindings = m.pad_bind.to_string_vec();
+                bindings.push(m.bind.to_string());
+                bindings
+            }
+            MappingType::CancelCast(m) => vec![m.bind.to_string()],
+            MappingType::Observation(m) => vec![m.bind.to_string()],
+            MappingType::Fps(m) => {
+                let mut bindings = vec![m.bind.to_string()];
+                bindings.extend(m.ads.iter().map(|ads| ads.bind.to_string()));
+                bindings.extend(m.presets.iter().map(|preset| preset.bind.to_string()));
+                bindings
+            }
+            MappingType::Fire(m) => vec![m.bind.to_string()],
+            MappingType::RawInput(m) => vec![m.bind.to_string()],
+            MappingType::Script(m) => vec![m.bind.to_string()],
+            MappingType::SwitchMapping(m) => vec![m.bind.to_string()],
+        };
+        bindings
+            .into_iter()
+            .map(|binding| {
+                let mut buttons: Vec<&str> = binding.split('+').collect();
+                buttons.sort();
+                buttons.join("+")
+            })
+            .collect()
+    }
+
+    /// All button bindings of the mapping, joystick axes are not included.
+    pub fn button_bindings(&self) -> Vec<&ButtonBinding> {
+        match self {
+            MappingType::SingleTap(m) => vec![&m.bind],
+            MappingType::RepeatTap(m) => vec![&m.bind],
+            MappingType::MultipleTap(m) => vec![&m.bind],
+            MappingType::Swipe(m) => vec![&m.bind],
+            MappingType::Gesture(m) => vec![&m.bind],
+            MappingType::Scroll(m) => vec![&m.bind],
+            MappingType::DirectionPad(m) => {
+                let mut bindings = m.bind.buttons();
+                bindings.extend(m.modifiers.iter().map(|modifier| &modifier.bind));
+                bindings
+            }
+            MappingType::MouseCastSpell(m) => vec![&m.bind],
+            MappingType::PadCastSpell(m) => {
+                let mut bindings = m.pad_bind.buttons();
+                bindings.push(&m.bind);
+                bindings
+            }
+            MappingType::CancelCast(m) => vec![&m.bind],
+            MappingType::Observation(m) => vec![&m.bind],
+            MappingType::Fps(m) => {
+                let mut bindings = vec![&m.bind];
+                bindings.extend(m.ads.iter().map(|ads| &ads.bind));
+                bindings.extend(m.presets.iter().map(|preset| &preset.bind));
+                bindings
+            }
+            MappingType::Fire(m) => vec![&m.bind],
+            MappingType::RawInput(m) => vec![&m.bind],
+            MappingType::Script(m) => vec![&m.bind],
+            MappingType::SwitchMapping(m) => vec![&m.bind],
+        }
+    }
+
+    pub fn positions_mut(&mut self) -> Vec<&mut Position> {
+        match self {
+            MappingType::SingleTap(m) => vec![&mut m.position],
+            MappingType::RepeatTap(m) => vec![&mut m.position],
+            MappingType::MultipleTap(m) => m.items.iter_mut().map(|i| &mut i.position).collect(),
+            MappingType::Swipe(m) => m
+                .positions
+                .iter_mut()
+                .chain(
+                    m.segments
+                        .iter_mut()
+                        .flat_map(|s| s.control_points.iter_mut()),
+                )
+                .collect(),
+            MappingType::Gesture(m) => vec![&mut m.position],
+            MappingType::Scroll(m) => vec![&mut m.position],
+            MappingType::DirectionPad(m) => vec![&mut m.position],
+            MappingType::MouseCastSpell(m) => vec![&mut m.position, &mut m.center],
+            MappingType::PadCastSpell(m) => vec![&mut m.position],
+            MappingType::CancelCast(m) => vec![&mut m.position],
+            MappingType::Observation(m) => vec![&mut m.position],
+            MappingType::Fps(m) => vec![&mut m.position],
+            MappingType::Fire(m) => vec![&mut m.position],
+            MappingType::RawInput(m) => vec![&mut m.position],
+            MappingType::Script(m) => vec![&mut m.position],
+            MappingType::SwitchMapping(m) => vec![&mut m.position],
+        }
+    }
+}
+
+impl MappingConfig {
+    /// Mappings of the base and all layers.
+    pub fn all_mappings(&self) -> impl Iterator<Item = &MappingType> {
+        self.mappings
+            .iter()
+            .chain(self.layers.iter().flat_map(|layer| layer.mappings.iter()))
+    }
+
+    pub fn all_mappings_mut(&mut self) -> impl Iterator<Item = &mut MappingType> {
+        self.mappings.iter_mut().chain(
+            self.layers
+                .iter_mut()
+                .flat_map(|layer| layer.mappings.iter_mut()),
+        )
+    }
+
+    /// Resolve relative and anchored positions against the size of the connected device.
+    /// A zero `device_size` means no device is connected, in which case the original size is used.
+    pub fn resolve_positions(&mut self, device_size: Size) {
+        let original_size: Vec2 = self.original_size.into();
+        let device_size: Vec2 = device_size.into();
+        for mapping in self.all_mappings_mut() {
+            for position in mapping.positions_mut() {
+                position.resolve(original_size, device_size);
+            }
+        }
+    }
+}
+
 pub fn default_mapping_config() -> MappingConfig {
     MappingConfig {
-        version: "0.0.1".to_string(),
+        version: MAPPING_CONFIG_VERSION.to_string(),
         original_size: Size {
             width: 2560,
             height: 1440,
         },
         mappings: vec![],
+        layers: vec![],
     }
 }
 
-pub fn validate_mapping_config(mapping_config: &MappingConfig) -> Result<(), String> {
-    let mut validate_errors = Vec::<String>::new();
-
-    let mut mapping_type_map = HashMap::<String, u32>::new();
-    for mapping in mapping_config.mappings.iter() {
-        let name = mapping.as_ref();
-        let count = *mapping_type_map
-            .entry(name.to_string())
-            .and_modify(|c| *c += 1)
-            .or_insert(1);
-        if count > 32 {
-            validate_errors.push(
-                t!(
-                    "mask.mapping.mappingActionExceedsMaxCount",
-                    name => name,
-                    count => count,
-                    max => 32
-                )
-                .to_string(),
-            );
-        }
-
-        if let Err(e) = mapping.validate() {
-            validate_errors.push(format!("[{name}-{count}] {e}"));
-        }
-    }
-
-    if !validate_errors.is_empty() {
-        let mut validate_errors: Vec<String> = validate_errors
+/// Returns the warnings if the config is valid, otherwise the errors joined as a message.
+pub fn validate_mapping_config(
+    mapping_config: &MappingConfig,
+) -> Result<Vec<MappingIssue>, String> {
+    let (errors, warnings): (Vec<MappingIssue>, Vec<MappingIssue>) =
+        check_mapping_config(mapping_config)
+            .into_iter()
+            .partition(|issue| issue.level == IssueLevel::Error);
+    if !errors.is_empty() {
+        let mut validate_errors: Vec<String> = errors
             .into_iter()
             .enumerate()
             .map(|(i, err)| format!("{}. {}", i + 1, err))
@@ -345,12 +790,14 @@ pub fn validate_mapping_config(mapping_config: &MappingConfig) -> Result<(), Str
         );
         return Err(validate_errors.join("\n"));
     }
-    Ok(())
+    Ok(warnings)
 }
 
+/// Load a mapping config to activate. Queue `apply_mapping_input` after activating it.
 pub fn load_mapping_config(
     file_name: impl AsRef<str>,
-) -> Result<(BindMappingConfig, InputConfig), String> {
+    device_size: Size,
+) -> Result<BindMappingConfig, String> {
     if !is_safe_file_name(file_name.as_ref()) {
         return Err(format!(
             "{}: {}",
@@ -369,16 +816,65 @@ pub fn load_mapping_config(
         ));
     }
 
+    let (mut mapping_config, _) = read_mapping_config(&path)?;
+
+    for warning in validate_mapping_config(&mapping_config)? {
+        log::warn!("[Mapping] {}: {}", file_name.as_ref(), warning);
+    }
+    mapping_config.resolve_positions(device_size);
+
+    Ok(mapping_config.into())
+}
+
+/// Deserialize a raw mapping config after upgrading it to the current version.
+pub fn parse_mapping_value(mut value: Value) -> Result<(MappingConfig, Vec<String>), String> {
+    let migrations = upgrade_mapping_config(&mut value)?;
+    let mapping_config: MappingConfig = serde_json::from_value(value)
+        .map_err(|e| format!("{}: {}", t!("web.mapping.cannotDeserializeConfig"), e))?;
+    Ok((mapping_config, migrations))
+}
+
+/// Read a mapping config file and upgrade it to the current version if needed.
+/// An upgraded file is saved in place, and the original is kept as `<file>.<version>.bak`.
+/// Returns the config and the migrations that ran.
+pub fn read_mapping_config(path: &Path) -> Result<(MappingConfig, Vec<String>), String> {
     let config_string = std::fs::read_to_string(path)
         .map_err(|e| format!("{}: {}", t!("web.mapping.cannotReadMappingConfig"), e))?;
-    let mapping_config: MappingConfig = serde_json::from_str(&config_string)
+    let value: Value = serde_json::from_str(&config_string)
         .map_err(|e| format!("{}: {}", t!("web.mapping.cannotDeserializeConfig"), e))?;
+    let old_version = value
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let (mapping_config, migrations) = parse_mapping_value(value)?;
+    if !migrations.is_empty() {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let backup_path = path.with_file_name(format!("{}.{}.bak", file_name, old_version));
+        copy(path, &backup_path)
+            .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotBackupMappingConfig"), e))?;
+        save_mapping_config(&mapping_config, path)?;
+        log::info!(
+            "[Mapping] {}",
+            t!(
+                "mask.mapping.mappingUpgraded",
+                file => file_name,
+                migrations => migrations.join(", "),
+                backup => backup_path.file_name().unwrap_or_default().to_string_lossy()
+            )
+        );
+    }
+    Ok((mapping_config, migrations))
+}
 
-    validate_mapping_config(&mapping_config)?;
+// modified time of the mapping files last written by the app, by path
+static SAVED_MAPPING_TIMES: Lazy<Mutex<HashMap<PathBuf, SystemTime>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
-    let bind_mapping_config: BindMappingConfig = mapping_config.into();
-    let input_config: InputConfig = InputConfig::from(&bind_mapping_config);
-    Ok((bind_mapping_config, input_config))
+/// Whether the file was last modified by `save_mapping_config`, not edited by others.
+pub fn is_saved_by_app(path: &Path, modified: SystemTime) -> bool {
+    SAVED_MAPPING_TIMES.lock().unwrap().get(path) == Some(&modified)
 }
 
 pub fn save_mapping_config(config: &MappingConfig, path: &Path) -> Result<(), String> {
@@ -393,6 +889,78 @@ pub fn save_mapping_config(config: &MappingConfig, path: &Path) -> Result<(), St
         .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotCreateMappingConfig"), e))?;
     file.write_all(json_string.as_bytes())
         .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotWriteMappingConfig"), e))?;
+    if let Ok(modified) = file.metadata().and_then(|metadata| metadata.modified()) {
+        SAVED_MAPPING_TIMES
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), modified);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn config_0_0_1() -> Value {
+        json!({
+            "version": "0.0.1",
+            "original_size": { "width": 1280, "height": 720 },
+            "mappings": [{
+                "type": "SingleTap",
+                "position": { "x": 100.0, "y": 200.0 },
+                "note": "",
+                "pointer_id": 1,
+                "duration": 50,
+                "sync": false,
+                "bind": ["KeyA"]
+            }]
+        })
+    }
+
+    #[test]
+    fn upgrade_mapping_value() {
+        let (config, migrations) = parse_mapping_value(config_0_0_1()).unwrap();
+        assert_eq!(migrations, vec!["0.0.1 -> 0.0.2"]);
+        assert_eq!(config.version, MAPPING_CONFIG_VERSION);
+        assert_eq!(config.mappings.len(), 1);
+        assert!(config.layers.is_empty());
+
+        let mut value = config_0_0_1();
+        value["version"] = Value::from("9.9.9");
+        assert!(parse_mapping_value(value).is_err());
+        let mut value = config_0_0_1();
+        value.as_object_mut().unwrap().remove("version");
+        assert!(parse_mapping_value(value).is_err());
+    }
+
+    #[test]
+    fn upgrade_mapping_file_in_place() {
+        let dir = std::env::temp_dir().join(format!("scrcpy-mask-upgrade-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("old.json");
+        let old_string = to_string_pretty(&config_0_0_1()).unwrap();
+        std::fs::write(&path, &old_string).unwrap();
+
+        let (config, migrations) = read_mapping_config(&path).unwrap();
+        assert_eq!(migrations, vec!["0.0.1 -> 0.0.2"]);
+        assert_eq!(config.version, MAPPING_CONFIG_VERSION);
+
+        // the original is kept, and the upgraded config replaces it
+        let backup = std::fs::read_to_string(dir.join("old.json.0.0.1.bak")).unwrap();
+        assert_eq!(backup, old_string);
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], MAPPING_CONFIG_VERSION);
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert!(is_saved_by_app(&path, modified));
+
+        // nothing left to upgrade
+        let (_, migrations) = read_mapping_config(&path).unwrap();
+        assert!(migrations.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,7 +2,12 @@ use bevy::{input::mouse::AccumulatedMouseMotion, prelude::*, window::CursorGrabM
 
 use crate::{
     mask::{
-        mapping::{MappingState, utils::ControlMsgHelper},
+        mapping::{
+            MappingState,
+            config::MappingAction,
+            fire::{AimAcceleration, FpsRecenter},
+            utils::ControlMsgHelper,
+        },
         mask_command::MaskSize,
     },
     scrcpy::constant::MotionEventAction,
@@ -54,11 +59,27 @@ impl Plugin for CursorPlugins {
 pub struct ActiveCursorFpsConfig {
     pub ignore_fps_motion: bool,
     pub sensitivity: Vec2,
+    pub acceleration: AimAcceleration,
+    // set while aiming down sights
+    pub ads_multiplier: Option<f32>,
+    // fps mapping in use and its sensitivity preset
+    pub action: Option<MappingAction>,
+    pub preset: Option<usize>,
+    pub recenter: FpsRecenter,
+    // current pointer, which takes turns with the alternate one of `recenter`
     pub pointer_id: u64,
     pub original_pos: Vec2,
     pub original_size: Vec2,
 }
 
+impl ActiveCursorFpsConfig {
+    /// Move of the touch for the mouse motion `delta` in `secs`.
+    pub fn aim_delta(&self, delta: Vec2, sensitivity: Vec2, secs: f32) -> Vec2 {
+        let speed = delta.length() / (secs * 1000.).max(1.);
+        delta * sensitivity * self.acceleration.gain(speed) * self.ads_multiplier.unwrap_or(1.)
+    }
+}
+
 fn handle_cursor_normal(
     accumulated_motion: Res<AccumulatedMouseMotion>,
     window: Single<&Window>,
@@ -124,8 +145,9 @@ fn run_if_handle_cursor_fps(
 
 fn handle_cursor_fps(
     accumulated_motion: Res<AccumulatedMouseMotion>,
+    time: Res<Time>,
     mut cursor_pos: ResMut<CursorPosition>,
-    fps_config: Res<ActiveCursorFpsConfig>,
+    mut fps_config: ResMut<ActiveCursorFpsConfig>,
     mut ignore_first_motion: ResMut<IgnoreFirstMotion>,
     mask_size: Res<MaskSize>,
     cs_tx_res: Res<ChannelSenderCS>,
@@ -139,77 +161,101 @@ fn handle_cursor_fps(
         return;
     }
 
-    let mut new_pos = cursor_pos.0 + accumulated_motion.delta * fps_config.sensitivity;
+    let mut new_pos = cursor_pos.0
+        + fps_config.aim_delta(
+            accumulated_motion.delta,
+            fps_config.sensitivity,
+            time.delta_secs(),
+        );
 
-    let is_out_of_bounds = |pos: Vec2| -> bool {
-        pos.x < FPS_MARGIN
-            || pos.x > mask_size.0.x - FPS_MARGIN
-            || pos.y < FPS_MARGIN
-            || pos.y > mask_size.0.y - FPS_MARGIN
+    // recenter distances are in mapping px, except for the default margin
+    let scale = mask_size.0 / fps_config.original_size;
+    let center_pos = fps_config.original_pos * scale;
+    let margin = match fps_config.recenter.margin {
+        Some(margin) => margin * scale,
+        None => Vec2::splat(FPS_MARGIN),
     };
+    let max_drag = fps_config.recenter.max_drag;
 
-    if is_out_of_bounds(new_pos) {
-        let center_pos = fps_config.original_pos / fps_config.original_size * mask_size.0;
-        let mut delta = new_pos - cursor_pos.0;
-        // move to the edge and touch up
-        let edge_pos = Vec2::new(
-            new_pos.x.clamp(FPS_MARGIN, mask_size.0.x - FPS_MARGIN),
-            new_pos.y.clamp(FPS_MARGIN, mask_size.0.y - FPS_MARGIN),
-        );
-        delta -= edge_pos - cursor_pos.0;
+    let needs_recenter = |pos: Vec2| -> bool {
+        let out_of_bounds = pos.x < margin.x
+            || pos.x > mask_size.0.x - margin.x
+            || pos.y < margin.y
+            || pos.y > mask_size.0.y - margin.y;
+        let too_far = max_drag > 0. && ((pos - center_pos) / scale).length() > max_drag;
+        out_of_bounds || too_far
+    };
+    // the last position before re-centering
+    let edge_of = |pos: Vec2| -> Vec2 {
+        let mut pos = pos;
+        let offset = (pos - center_pos) / scale;
+        if max_drag > 0. && offset.length() > max_drag {
+            pos = center_pos + offset.normalize() * max_drag * scale;
+        }
+        Vec2::new(
+            pos.x.clamp(margin.x, mask_size.0.x - margin.x),
+            pos.y.clamp(margin.y, mask_size.0.y - margin.y),
+        )
+    };
+    let mut recenter = |edge_pos: Vec2| {
+        let pointer_id = fps_config.pointer_id;
+        // move to the edge
         ControlMsgHelper::send_touch(
             &cs_tx_res.0,
             MotionEventAction::Move,
-            fps_config.pointer_id,
-            mask_size.0,
-            edge_pos,
-        );
-        ControlMsgHelper::send_touch(
-            &cs_tx_res.0,
-            MotionEventAction::Up,
-            fps_config.pointer_id,
+            pointer_id,
             mask_size.0,
             edge_pos,
         );
-        // touch down center
-        ControlMsgHelper::send_touch(
-            &cs_tx_res.0,
-            MotionEventAction::Down,
-            fps_config.pointer_id,
-            mask_size.0,
-            center_pos,
-        );
+        match fps_config.recenter.alternate_pointer_id {
+            Some(alternate_pointer_id) => {
+                // touch down center with the other pointer before touch up, then swap them
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    alternate_pointer_id,
+                    mask_size.0,
+                    center_pos,
+                );
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Up,
+                    pointer_id,
+                    mask_size.0,
+                    edge_pos,
+                );
+                fps_config.pointer_id = alternate_pointer_id;
+                fps_config.recenter.alternate_pointer_id = Some(pointer_id);
+            }
+            None => {
+                // touch up and touch down center
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Up,
+                    pointer_id,
+                    mask_size.0,
+                    edge_pos,
+                );
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    mask_size.0,
+                    center_pos,
+                );
+            }
+        }
+    };
+
+    if needs_recenter(new_pos) {
+        let edge_pos = edge_of(new_pos);
+        let delta = new_pos - edge_pos;
+        recenter(edge_pos);
         new_pos = center_pos + delta;
-        if is_out_of_bounds(new_pos) {
-            // still out of bounds
-            // move to the edge and touch up
-            let edge_pos = Vec2::new(
-                new_pos.x.clamp(FPS_MARGIN, mask_size.0.x - FPS_MARGIN),
-                new_pos.y.clamp(FPS_MARGIN, mask_size.0.y - FPS_MARGIN),
-            );
-            ControlMsgHelper::send_touch(
-                &cs_tx_res.0,
-                MotionEventAction::Move,
-                fps_config.pointer_id,
-                mask_size.0,
-                edge_pos,
-            );
-            ControlMsgHelper::send_touch(
-                &cs_tx_res.0,
-                MotionEventAction::Up,
-                fps_config.pointer_id,
-                mask_size.0,
-                edge_pos,
-            );
-            // touch down center
-            ControlMsgHelper::send_touch(
-                &cs_tx_res.0,
-                MotionEventAction::Down,
-                fps_config.pointer_id,
-                mask_size.0,
-                center_pos,
-            );
-            new_pos = edge_pos;
+        if needs_recenter(new_pos) {
+            // still out of range, drop the rest of the motion
+            recenter(edge_of(new_pos));
+            new_pos = center_pos;
         } else {
             // move to finnal pos
             ControlMsgHelper::send_touch(
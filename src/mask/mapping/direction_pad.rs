@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    f32::consts::PI,
     time::{Duration, Instant},
 };
 
@@ -10,15 +11,17 @@ use bevy::{
     },
     math::Vec2,
 };
-use bevy_ineffable::prelude::{Ineffable, InputBinding};
+use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding};
 use bevy_tokio_tasks::TokioTasksRuntime;
+use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
 use crate::{
     mask::mapping::{
-        binding::{DirectionBinding, ValidateMappingConfig},
-        config::ActiveMappingConfig,
+        binding::{ButtonBinding, DirectionBinding, ValidateMappingConfig},
+        config::{ActiveMappingConfig, MappingAction},
         utils::{ControlMsgHelper, MIN_MOVE_STEP_INTERVAL, Position, ease_sigmoid_like},
     },
     scrcpy::constant::MotionEventAction,
@@ -38,6 +41,8 @@ pub struct BindMappingDirectionPad {
     pub initial_duration: u64,
     pub max_offset_x: f32,
     pub max_offset_y: f32,
+    pub stick: StickResponse,
+    pub modifiers: Vec<BindDirectionPadModifier>,
     pub bind: DirectionBinding,
     pub input_binding: InputBinding,
 }
@@ -51,13 +56,19 @@ impl From<MappingDirectionPad> for BindMappingDirectionPad {
             initial_duration: value.initial_duration,
             max_offset_x: value.max_offset_x,
             max_offset_y: value.max_offset_y,
+            stick: value.stick,
+            modifiers: value
+                .modifiers
+                .into_iter()
+                .map(|modifier| modifier.into())
+                .collect(),
             bind: value.bind.clone(),
             input_binding: value.bind.into(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingDirectionPad {
     pub note: String,
     pub pointer_id: u64,
@@ -65,10 +76,190 @@ pub struct MappingDirectionPad {
     pub initial_duration: u64,
     pub max_offset_x: f32,
     pub max_offset_y: f32,
+    /// only used by a `JoyStick` binding
+    #[serde(default)]
+    pub stick: StickResponse,
+    /// the first held modifier is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<DirectionPadModifier>,
     pub bind: DirectionBinding,
 }
 
-impl ValidateMappingConfig for MappingDirectionPad {}
+impl ValidateMappingConfig for MappingDirectionPad {
+    fn validate(&self) -> Result<(), String> {
+        for modifier in self.modifiers.iter() {
+            if modifier.scale <= 0. {
+                return Err(t!(
+                    "mask.mapping.invalidPadModifierScale",
+                    bind => modifier.bind.to_string()
+                )
+                .to_string());
+            }
+        }
+        self.stick.validate()
+    }
+}
+
+/// Scale the offset of the pad while the key is held.
+/// Below 1 to walk, above 1 to push beyond the pad edge, such as to trigger auto-run.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct DirectionPadModifier {
+    pub scale: f32,
+    pub bind: ButtonBinding,
+}
+
+#[derive(Debug, Clone)]
+pub struct BindDirectionPadModifier {
+    pub scale: f32,
+    pub action: MappingAction,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<DirectionPadModifier> for BindDirectionPadModifier {
+    fn from(value: DirectionPadModifier) -> Self {
+        Self {
+            scale: value.scale,
+            action: MappingAction::default(), // temp value
+            bind: value.bind.clone(),
+            input_binding: ContinuousBinding::hold(value.bind).0,
+        }
+    }
+}
+
+impl BindMappingDirectionPad {
+    fn modifier_scale(&self, ineffable: &Ineffable) -> f32 {
+        self.modifiers
+            .iter()
+            .find(|modifier| ineffable.is_active(modifier.action.ineff_continuous()))
+            .map_or(1., |modifier| modifier.scale)
+    }
+}
+
+/// Whether the deadzones apply to the length of the stick vector or to each axis.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+pub enum DeadzoneShape {
+    #[default]
+    Radial,
+    Axial,
+}
+
+/// Maps the stick input out of the deadzones to the output, both from 0 to 1.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(tag = "type")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    Quadratic,
+    /// straight lines through `points` between (0, 0) and (1, 1), sorted by x
+    Custom {
+        points: Vec<[f32; 2]>,
+    },
+}
+
+impl ResponseCurve {
+    fn apply(&self, t: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => t,
+            ResponseCurve::Quadratic => t * t,
+            ResponseCurve::Custom { points } => {
+                let mut last = [0., 0.];
+                for &point in points.iter().chain([[1., 1.]].iter()) {
+                    if t <= point[0] {
+                        let span = point[0] - last[0];
+                        if span <= 0. {
+                            return point[1];
+                        }
+                        return last[1] + (point[1] - last[1]) * (t - last[0]) / span;
+                    }
+                    last = point;
+                }
+                1.
+            }
+        }
+    }
+}
+
+/// Limit the stick to a few directions, like a digital pad.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+pub enum DirectionSnap {
+    #[default]
+    None,
+    FourWay,
+    EightWay,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct StickResponse {
+    /// input below it is ignored, from 0 to 1
+    pub inner_deadzone: f32,
+    /// input within it from the edge is treated as full, from 0 to 1
+    pub outer_deadzone: f32,
+    pub deadzone_shape: DeadzoneShape,
+    pub curve: ResponseCurve,
+    pub snap: DirectionSnap,
+}
+
+impl StickResponse {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.inner_deadzone < 0.
+            || self.outer_deadzone < 0.
+            || self.inner_deadzone + self.outer_deadzone >= 1.
+        {
+            return Err(t!(
+                "mask.mapping.invalidStickDeadzone",
+                inner => self.inner_deadzone,
+                outer => self.outer_deadzone
+            )
+            .to_string());
+        }
+        if let ResponseCurve::Custom { points } = &self.curve {
+            let mut last_x = 0.;
+            for point in points.iter() {
+                if point[0] <= last_x || point[0] >= 1. || !(0.0..=1.0).contains(&point[1]) {
+                    return Err(t!("mask.mapping.invalidResponseCurve").to_string());
+                }
+                last_x = point[0];
+            }
+        }
+        Ok(())
+    }
+
+    // rescale the input between the deadzones and apply the curve
+    fn response(&self, input: f32) -> f32 {
+        let live = 1. - self.inner_deadzone - self.outer_deadzone;
+        let t = ((input - self.inner_deadzone) / live).clamp(0., 1.);
+        self.curve.apply(t)
+    }
+
+    /// Stick state after deadzones, curve and snapping, in the unit circle for radial deadzones.
+    pub fn apply(&self, raw: Vec2) -> Vec2 {
+        let state = match self.deadzone_shape {
+            DeadzoneShape::Radial => {
+                let length = raw.length();
+                if length <= self.inner_deadzone || length == 0. {
+                    return Vec2::ZERO;
+                }
+                raw / length * self.response(length)
+            }
+            DeadzoneShape::Axial => Vec2::new(
+                raw.x.signum() * self.response(raw.x.abs()),
+                raw.y.signum() * self.response(raw.y.abs()),
+            ),
+        };
+        let step = match self.snap {
+            DirectionSnap::None => return state,
+            DirectionSnap::FourWay => PI / 2.,
+            DirectionSnap::EightWay => PI / 4.,
+        };
+        if state == Vec2::ZERO {
+            return state;
+        }
+        let angle = (state.to_angle() / step).round() * step;
+        Vec2::from_angle(angle) * state.length().min(1.)
+    }
+}
 
 #[derive(Resource, Default)]
 pub struct DirectionPadMap(pub HashMap<String, DirectionPadItem>);
@@ -79,6 +270,22 @@ pub struct DirectionPadItem {
     pub original_size: Vec2,
     pub original_pos: Vec2,
     pub last_state: Vec2,
+    pub last_scale: f32,
+}
+
+impl DirectionPadMap {
+    pub fn release(&mut self, key: &str, cs_tx: &ChannelSenderCS) {
+        if let Some(item) = self.0.remove(key) {
+            // touch up and remove state
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Up,
+                item.pointer_id,
+                item.original_size,
+                item.original_pos + item.last_state,
+            );
+        }
+    }
 }
 
 fn scale_direction_2d_state(d_state: Vec2, mapping: &BindMappingDirectionPad) -> Vec2 {
@@ -114,6 +321,35 @@ fn scale_direction_2d_state(d_state: Vec2, mapping: &BindMappingDirectionPad) ->
 #[derive(Resource, Default)]
 pub struct BlockDirectionPad(pub bool);
 
+// move the touch from `from` to `to` in `duration` ms, eased
+fn smooth_move(
+    runtime: &TokioTasksRuntime,
+    cs_tx: &ChannelSenderCS,
+    pointer_id: u64,
+    original_size: Vec2,
+    from: Vec2,
+    to: Vec2,
+    duration: u64,
+) {
+    let cs_tx = cs_tx.0.clone();
+    let steps: u64 = std::cmp::max(1, duration / MIN_MOVE_STEP_INTERVAL);
+    runtime.spawn_background_task(move |_ctx| async move {
+        for step in 1..=steps {
+            let linear_t = step as f32 / steps as f32;
+            let eased_t = ease_sigmoid_like(linear_t);
+            let interp = from + (to - from) * eased_t;
+            ControlMsgHelper::send_touch(
+                &cs_tx,
+                MotionEventAction::Move,
+                pointer_id,
+                original_size,
+                interp,
+            );
+            sleep(Duration::from_millis(MIN_MOVE_STEP_INTERVAL)).await;
+        }
+    });
+}
+
 pub fn handle_direction_pad(
     ineffable: Res<Ineffable>,
     active_mapping: Res<ActiveMappingConfig>,
@@ -127,93 +363,97 @@ pub fn handle_direction_pad(
     }
 
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("DirectionPad") {
-                let mapping = mapping.as_ref_directionpad();
-                let key = action.to_string();
-                let original_size: Vec2 = active_mapping.original_size.into();
-                let state = scale_direction_2d_state(
-                    ineffable.direction_2d(action.ineff_dual_axis()),
-                    mapping,
-                );
-                if direction_pad_map.0.contains_key(&key) {
-                    let item = direction_pad_map.0.get_mut(&key).unwrap();
-                    if item.enable_instant > Instant::now() {
-                        // wait for initial duration
-                        continue;
-                    }
-                    let original_pos: Vec2 = mapping.position.into();
-                    if state.x == 0.0 && state.y == 0.0 {
-                        // touch up and remove state
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Up,
-                            mapping.pointer_id,
-                            original_size,
-                            original_pos + item.last_state,
-                        );
-                        direction_pad_map.0.remove(&key);
-                    } else if state != item.last_state {
-                        // record new state
-                        item.last_state = state;
-                        // move to new state
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Move,
-                            mapping.pointer_id,
-                            original_size,
-                            original_pos + state,
-                        );
-                    }
-                } else if state.x != 0.0 || state.y != 0.0 {
-                    let pointer_id = mapping.pointer_id;
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let original_pos: Vec2 = mapping.position.into();
-
-                    let enable_instant = Instant::now()
-                        + Duration::from_millis(mapping.initial_duration + MIN_MOVE_STEP_INTERVAL);
-
-                    // record new item
-                    direction_pad_map.0.insert(
-                        key,
-                        DirectionPadItem {
-                            enable_instant,
-                            pointer_id,
-                            original_size,
-                            original_pos: original_pos,
-                            last_state: state,
-                        },
+        for (action, mapping) in &active_mapping.index.direction_pad {
+            let key = action.to_string();
+            let original_size: Vec2 = active_mapping.original_size.into();
+            let mut d_state = ineffable.direction_2d(action.ineff_dual_axis());
+            if let DirectionBinding::JoyStick { .. } = mapping.bind {
+                d_state = mapping.stick.apply(d_state);
+            }
+            let scale = mapping.modifier_scale(&ineffable);
+            let state = scale_direction_2d_state(d_state, mapping) * scale;
+            if direction_pad_map.0.contains_key(&key) {
+                let item = direction_pad_map.0.get_mut(&key).unwrap();
+                if item.enable_instant > Instant::now() {
+                    // wait for initial duration
+                    continue;
+                }
+                let original_pos: Vec2 = mapping.position.into();
+                if state.x == 0.0 && state.y == 0.0 {
+                    // touch up and remove state
+                    ControlMsgHelper::send_touch(
+                        &cs_tx_res.0,
+                        MotionEventAction::Up,
+                        mapping.pointer_id,
+                        original_size,
+                        original_pos + item.last_state,
                     );
-                    // touch down
-                    let cs_tx = cs_tx_res.0.clone();
+                    direction_pad_map.0.remove(&key);
+                } else if scale != item.last_scale {
+                    // modifier changed, move to new state with initial_duration
+                    smooth_move(
+                        &runtime,
+                        &cs_tx_res,
+                        mapping.pointer_id,
+                        original_size,
+                        original_pos + item.last_state,
+                        original_pos + state,
+                        mapping.initial_duration,
+                    );
+                    item.enable_instant = Instant::now()
+                        + Duration::from_millis(mapping.initial_duration + MIN_MOVE_STEP_INTERVAL);
+                    item.last_state = state;
+                    item.last_scale = scale;
+                } else if state != item.last_state {
+                    // record new state
+                    item.last_state = state;
+                    // move to new state
                     ControlMsgHelper::send_touch(
-                        &cs_tx,
-                        MotionEventAction::Down,
-                        pointer_id,
+                        &cs_tx_res.0,
+                        MotionEventAction::Move,
+                        mapping.pointer_id,
                         original_size,
-                        original_pos,
+                        original_pos + state,
                     );
-                    // move to state with initial_duration
-                    let delta = state;
-                    let steps: u64 =
-                        std::cmp::max(1, mapping.initial_duration / MIN_MOVE_STEP_INTERVAL);
-
-                    runtime.spawn_background_task(move |_ctx| async move {
-                        for step in 1..=steps {
-                            let linear_t = step as f32 / steps as f32;
-                            let eased_t = ease_sigmoid_like(linear_t);
-                            let interp = original_pos + delta * eased_t;
-                            ControlMsgHelper::send_touch(
-                                &cs_tx,
-                                MotionEventAction::Move,
-                                pointer_id,
-                                original_size,
-                                interp,
-                            );
-                            sleep(Duration::from_millis(MIN_MOVE_STEP_INTERVAL)).await;
-                        }
-                    });
                 }
+            } else if state.x != 0.0 || state.y != 0.0 {
+                let pointer_id = mapping.pointer_id;
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let original_pos: Vec2 = mapping.position.into();
+
+                let enable_instant = Instant::now()
+                    + Duration::from_millis(mapping.initial_duration + MIN_MOVE_STEP_INTERVAL);
+
+                // record new item
+                direction_pad_map.0.insert(
+                    key,
+                    DirectionPadItem {
+                        enable_instant,
+                        pointer_id,
+                        original_size,
+                        original_pos: original_pos,
+                        last_state: state,
+                        last_scale: scale,
+                    },
+                );
+                // touch down
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    original_size,
+                    original_pos,
+                );
+                // move to state with initial_duration
+                smooth_move(
+                    &runtime,
+                    &cs_tx_res,
+                    pointer_id,
+                    original_size,
+                    original_pos,
+                    original_pos + state,
+                    mapping.initial_duration,
+                );
             }
         }
     }
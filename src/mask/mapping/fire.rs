@@ -8,16 +8,18 @@ use bevy::{
     input::mouse::AccumulatedMouseMotion,
     math::Vec2,
     state::state::{NextState, State},
+    time::Time,
 };
 use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding, PulseBinding};
 use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     mask::{
         mapping::{
             binding::{ButtonBinding, ValidateMappingConfig},
-            config::ActiveMappingConfig,
+            config::{ActiveMappingConfig, BindMappingType, MappingAction},
             cursor::{ActiveCursorFpsConfig, CursorPosition, CursorState, FPS_MARGIN},
             utils::{ControlMsgHelper, Position},
         },
@@ -31,6 +33,130 @@ pub fn fire_init(mut commands: Commands) {
     commands.insert_resource(ActiveFireMap::default());
 }
 
+/// Extra sensitivity for fast mouse moves, by the speed in px per ms.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(tag = "type")]
+pub enum AimAcceleration {
+    #[default]
+    None,
+    /// sensitivity is multiplied by `1 + acceleration * speed`, up to `cap`
+    Linear { acceleration: f32, cap: f32 },
+    /// sensitivity is multiplied by `1 + acceleration * speed ^ exponent`, up to `cap`
+    Power {
+        acceleration: f32,
+        exponent: f32,
+        cap: f32,
+    },
+}
+
+impl AimAcceleration {
+    pub fn validate(&self) -> Result<(), String> {
+        let (acceleration, exponent, cap) = match *self {
+            AimAcceleration::None => return Ok(()),
+            AimAcceleration::Linear { acceleration, cap } => (acceleration, 1., cap),
+            AimAcceleration::Power {
+                acceleration,
+                exponent,
+                cap,
+            } => (acceleration, exponent, cap),
+        };
+        if acceleration < 0. || exponent <= 0. || cap < 1. {
+            return Err(t!("mask.mapping.invalidAimAcceleration").to_string());
+        }
+        Ok(())
+    }
+
+    pub fn gain(&self, speed: f32) -> f32 {
+        match *self {
+            AimAcceleration::None => 1.,
+            AimAcceleration::Linear { acceleration, cap } => (1. + acceleration * speed).min(cap),
+            AimAcceleration::Power {
+                acceleration,
+                exponent,
+                cap,
+            } => (1. + acceleration * speed.powf(exponent)).min(cap),
+        }
+    }
+}
+
+/// When the touch of fps mode lifts and presses down at the mapped position again.
+/// Distances are in px of the mapping.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct FpsRecenter {
+    /// distance to the screen edge that triggers re-centering,
+    /// 25 px of the mask window if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin: Option<f32>,
+    /// also re-center once the touch is this far from the mapped position, disabled if 0
+    pub max_drag: f32,
+    /// press down with it before the current pointer lifts, then take turns
+    pub alternate_pointer_id: Option<u64>,
+}
+
+/// Multiply the sensitivity while the key is held, to aim down sights.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct FpsAds {
+    pub multiplier: f32,
+    pub bind: ButtonBinding,
+}
+
+#[derive(Debug, Clone)]
+pub struct BindFpsAds {
+    pub multiplier: f32,
+    pub action: MappingAction,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<FpsAds> for BindFpsAds {
+    fn from(value: FpsAds) -> Self {
+        Self {
+            multiplier: value.multiplier,
+            action: MappingAction::default(), // temp value
+            bind: value.bind.clone(),
+            input_binding: ContinuousBinding::hold(value.bind).0,
+        }
+    }
+}
+
+/// Sensitivity switched to by the key, such as for a weapon.
+/// Press the key of the active preset again to go back to the sensitivity of the mapping.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct SensitivityPreset {
+    pub name: String,
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    #[serde(default)]
+    pub acceleration: AimAcceleration,
+    pub bind: ButtonBinding,
+}
+
+#[derive(Debug, Clone)]
+pub struct BindSensitivityPreset {
+    pub name: String,
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    pub acceleration: AimAcceleration,
+    pub action: MappingAction,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<SensitivityPreset> for BindSensitivityPreset {
+    fn from(value: SensitivityPreset) -> Self {
+        Self {
+            name: value.name,
+            sensitivity_x: value.sensitivity_x,
+            sensitivity_y: value.sensitivity_y,
+            acceleration: value.acceleration,
+            action: MappingAction::default(), // temp value
+            bind: value.bind.clone(),
+            input_binding: PulseBinding::just_pressed(value.bind).0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BindMappingFps {
     pub note: String,
@@ -38,6 +164,10 @@ pub struct BindMappingFps {
     pub position: Position,
     pub sensitivity_x: f32,
     pub sensitivity_y: f32,
+    pub acceleration: AimAcceleration,
+    pub ads: Option<BindFpsAds>,
+    pub presets: Vec<BindSensitivityPreset>,
+    pub recenter: FpsRecenter,
     pub bind: ButtonBinding,
     pub input_binding: InputBinding,
 }
@@ -50,33 +180,94 @@ impl From<MappingFps> for BindMappingFps {
             position: value.position,
             sensitivity_x: value.sensitivity_x,
             sensitivity_y: value.sensitivity_y,
+            acceleration: value.acceleration,
+            ads: value.ads.map(|ads| ads.into()),
+            presets: value
+                .presets
+                .into_iter()
+                .map(|preset| preset.into())
+                .collect(),
+            recenter: value.recenter,
             bind: value.bind.clone(),
             input_binding: PulseBinding::just_pressed(value.bind).0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl BindMappingFps {
+    /// Sensitivity and acceleration of the preset, or of the mapping itself.
+    fn aim(&self, preset: Option<usize>) -> (Vec2, AimAcceleration) {
+        match preset.and_then(|index| self.presets.get(index)) {
+            Some(preset) => (
+                (preset.sensitivity_x, preset.sensitivity_y).into(),
+                preset.acceleration,
+            ),
+            None => (
+                (self.sensitivity_x, self.sensitivity_y).into(),
+                self.acceleration,
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingFps {
     pub note: String,
     pub pointer_id: u64,
     pub position: Position,
     pub sensitivity_x: f32,
     pub sensitivity_y: f32,
+    #[serde(default)]
+    pub acceleration: AimAcceleration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ads: Option<FpsAds>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<SensitivityPreset>,
+    #[serde(default)]
+    pub recenter: FpsRecenter,
     pub bind: ButtonBinding,
 }
 
 impl ValidateMappingConfig for MappingFps {
     fn validate(&self) -> Result<(), String> {
-        if self.position.x <= FPS_MARGIN as i32 || self.position.y <= FPS_MARGIN as i32 {
+        let recenter = &self.recenter;
+        let margin = recenter.margin.unwrap_or(FPS_MARGIN);
+        if margin < 0. || recenter.max_drag < 0. {
+            return Err(t!(
+                "mask.mapping.invalidFpsRecenter",
+                margin => margin,
+                max_drag => recenter.max_drag
+            )
+            .to_string());
+        }
+        if recenter.alternate_pointer_id == Some(self.pointer_id) {
+            return Err(t!(
+                "mask.mapping.fpsAlternatePointerDuplicated",
+                pointer_id => self.pointer_id
+            )
+            .to_string());
+        }
+        // anchored positions depend on the device size, so only absolute ones are checked
+        if self.position.anchor.is_absolute()
+            && (self.position.x <= margin || self.position.y <= margin)
+        {
             return Err(t!(
                 "mask.mapping.invalidPosition",
                 x => self.position.x,
                 y => self.position.y,
-                margin => FPS_MARGIN
+                margin => margin
             )
             .to_string());
         }
+        self.acceleration.validate()?;
+        if let Some(ads) = &self.ads {
+            if ads.multiplier <= 0. {
+                return Err(t!("mask.mapping.invalidAdsMultiplier").to_string());
+            }
+        }
+        for preset in self.presets.iter() {
+            preset.acceleration.validate()?;
+        }
         Ok(())
     }
 }
@@ -92,49 +283,94 @@ pub fn handle_fps(
     mask_size: Res<MaskSize>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("Fps") {
-                if ineffable.just_pulsed(action.ineff_pulse()) {
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    match state.get() {
-                        CursorState::Normal => {
-                            let mapping = mapping.as_ref_fps();
-                            let original_pos = mapping.position.into();
-                            fps_config.pointer_id = mapping.pointer_id;
-                            fps_config.original_pos = original_pos;
-                            fps_config.original_size = original_size;
-                            fps_config.ignore_fps_motion = false;
-                            fps_config.sensitivity =
-                                (mapping.sensitivity_x, mapping.sensitivity_y).into();
-                            // touch down center
-                            ControlMsgHelper::send_touch(
-                                &cs_tx_res.0,
-                                MotionEventAction::Down,
-                                mapping.pointer_id,
-                                original_size,
-                                original_pos,
-                            );
-                            next_state.set(CursorState::Fps);
-                            log::info!("[Cursor] {}", t!("mask.mapping.enterFpsMode"));
-                        }
-                        CursorState::Fps => {
-                            // touch up
-                            ControlMsgHelper::send_touch(
-                                &cs_tx_res.0,
-                                MotionEventAction::Up,
-                                0,
-                                mask_size.0, // cursor_pos is related to mask size
-                                cursor_pos.0,
-                            );
-                            next_state.set(CursorState::Normal);
-                            log::info!("[Cursor] {}", t!("mask.mapping.exitFpsMode"));
+        for (action, mapping) in &active_mapping.index.fps {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                let original_size: Vec2 = active_mapping.original_size.into();
+                match state.get() {
+                    CursorState::Normal => {
+                        let original_pos = mapping.position.into();
+                        fps_config.pointer_id = mapping.pointer_id;
+                        fps_config.original_pos = original_pos;
+                        fps_config.original_size = original_size;
+                        fps_config.ignore_fps_motion = false;
+                        // keep the preset when entering with the same mapping again
+                        if fps_config.action != Some(*action) {
+                            fps_config.action = Some(*action);
+                            fps_config.preset = None;
                         }
-                    };
-                    return;
-                }
+                        (fps_config.sensitivity, fps_config.acceleration) =
+                            mapping.aim(fps_config.preset);
+                        fps_config.ads_multiplier = None;
+                        fps_config.recenter = mapping.recenter;
+                        // touch down center
+                        ControlMsgHelper::send_touch(
+                            &cs_tx_res.0,
+                            MotionEventAction::Down,
+                            mapping.pointer_id,
+                            original_size,
+                            original_pos,
+                        );
+                        next_state.set(CursorState::Fps);
+                        log::info!("[Cursor] {}", t!("mask.mapping.enterFpsMode"));
+                    }
+                    CursorState::Fps => {
+                        // touch up
+                        ControlMsgHelper::send_touch(
+                            &cs_tx_res.0,
+                            MotionEventAction::Up,
+                            fps_config.pointer_id,
+                            mask_size.0, // cursor_pos is related to mask size
+                            cursor_pos.0,
+                        );
+                        next_state.set(CursorState::Normal);
+                        log::info!("[Cursor] {}", t!("mask.mapping.exitFpsMode"));
+                    }
+                };
+                return;
+            }
+        }
+    }
+}
+
+pub fn handle_fps_aim(
+    ineffable: Res<Ineffable>,
+    active_mapping: Res<ActiveMappingConfig>,
+    mut fps_config: ResMut<ActiveCursorFpsConfig>,
+) {
+    let (Some(active_mapping), Some(action)) = (&active_mapping.0, fps_config.action) else {
+        return;
+    };
+    let Some(BindMappingType::Fps(mapping)) = active_mapping.mappings.get(&action) else {
+        return;
+    };
+
+    for (index, preset) in mapping.presets.iter().enumerate() {
+        if ineffable.just_pulsed(preset.action.ineff_pulse()) {
+            fps_config.preset = if fps_config.preset == Some(index) {
+                None
+            } else {
+                Some(index)
+            };
+            (fps_config.sensitivity, fps_config.acceleration) = mapping.aim(fps_config.preset);
+            match fps_config.preset {
+                Some(_) => log::info!(
+                    "[Cursor] {}: {}",
+                    t!("mask.mapping.switchSensitivityPreset"),
+                    preset.name
+                ),
+                None => log::info!("[Cursor] {}", t!("mask.mapping.resetSensitivityPreset")),
             }
         }
     }
+
+    if let Some(ads) = &mapping.ads {
+        let ads_multiplier = ineffable
+            .is_active(ads.action.ineff_continuous())
+            .then_some(ads.multiplier);
+        if fps_config.ads_multiplier != ads_multiplier {
+            fps_config.ads_multiplier = ads_multiplier;
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -162,7 +398,7 @@ impl From<MappingFire> for BindMappingFire {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingFire {
     pub note: String,
     pub pointer_id: u64,
@@ -179,6 +415,8 @@ pub struct ActiveFireMap(HashMap<String, FireItem>);
 
 pub fn handle_fire_trigger(
     accumulated_motion: Res<AccumulatedMouseMotion>,
+    time: Res<Time>,
+    fps_config: Res<ActiveCursorFpsConfig>,
     cs_tx_res: Res<ChannelSenderCS>,
     mask_size: Res<MaskSize>,
     mut active_map: ResMut<ActiveFireMap>,
@@ -190,7 +428,11 @@ pub fn handle_fire_trigger(
     }
 
     for (_, fire_item) in active_map.0.iter_mut() {
-        fire_item.current_pos += accumulated_motion.delta * fire_item.sensitivity;
+        fire_item.current_pos += fps_config.aim_delta(
+            accumulated_motion.delta,
+            fire_item.sensitivity,
+            time.delta_secs(),
+        );
         ControlMsgHelper::send_touch(
             &cs_tx_res.0,
             MotionEventAction::Move,
@@ -207,6 +449,40 @@ struct FireItem {
     sensitivity: Vec2,
 }
 
+impl ActiveFireMap {
+    pub fn release(
+        &mut self,
+        key: &str,
+        cs_tx: &ChannelSenderCS,
+        mask_size: Vec2,
+        fps_config: &mut ActiveCursorFpsConfig,
+        cursor_pos: &mut CursorPosition,
+    ) {
+        if let Some(fire_item) = self.0.remove(key) {
+            // touch up fire
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Up,
+                fire_item.pointer_id,
+                mask_size,
+                fire_item.current_pos,
+            );
+            // touch down fps center
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Down,
+                fps_config.pointer_id,
+                fps_config.original_size,
+                fps_config.original_pos,
+            );
+            // set cursor pos to fps center
+            cursor_pos.0 = fps_config.original_pos / fps_config.original_size * mask_size;
+            // continue fps motion
+            fps_config.ignore_fps_motion = false;
+        }
+    }
+}
+
 pub fn handle_fire(
     ineffable: Res<Ineffable>,
     active_mapping: Res<ActiveMappingConfig>,
@@ -217,67 +493,48 @@ pub fn handle_fire(
     mask_size: Res<MaskSize>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("Fire") {
-                let mapping = mapping.as_ref_fire();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    // stop fps motion
-                    fps_config.ignore_fps_motion = true;
-                    // touch up fps
-                    ControlMsgHelper::send_touch(
-                        &cs_tx_res.0,
-                        MotionEventAction::Up,
-                        fps_config.pointer_id,
-                        mask_size.0,
-                        cursor_pos.0, // fps cursor pos
-                    );
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let original_pos: Vec2 = mapping.position.into();
-                    let sensitivity: Vec2 = (mapping.sensitivity_x, mapping.sensitivity_y).into();
-                    let pointer_id = mapping.pointer_id;
-                    let current_pos = original_pos / original_size * mask_size.0;
-                    // touch down fire
-                    ControlMsgHelper::send_touch(
-                        &cs_tx_res.0,
-                        MotionEventAction::Down,
+        for (action, mapping) in &active_mapping.index.fire {
+            if ineffable.just_activated(action.ineff_continuous()) {
+                // stop fps motion
+                fps_config.ignore_fps_motion = true;
+                // touch up fps
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Up,
+                    fps_config.pointer_id,
+                    mask_size.0,
+                    cursor_pos.0, // fps cursor pos
+                );
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let original_pos: Vec2 = mapping.position.into();
+                let sensitivity: Vec2 = (mapping.sensitivity_x, mapping.sensitivity_y).into();
+                let pointer_id = mapping.pointer_id;
+                let current_pos = original_pos / original_size * mask_size.0;
+                // touch down fire
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    original_size,
+                    original_pos,
+                );
+                // add to active_map
+                active_map.0.insert(
+                    action.to_string(),
+                    FireItem {
+                        current_pos, // independent pos
                         pointer_id,
-                        original_size,
-                        original_pos,
-                    );
-                    // add to active_map
-                    active_map.0.insert(
-                        action.to_string(),
-                        FireItem {
-                            current_pos, // independent pos
-                            pointer_id,
-                            sensitivity,
-                        },
-                    );
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    if let Some(fire_item) = active_map.0.remove(action.as_ref()) {
-                        // touch up fire
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Up,
-                            fire_item.pointer_id,
-                            mask_size.0,
-                            fire_item.current_pos,
-                        );
-                        // touch down fps center
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Down,
-                            fps_config.pointer_id,
-                            fps_config.original_size,
-                            fps_config.original_pos,
-                        );
-                        // set cursor pos to fps center
-                        cursor_pos.0 =
-                            fps_config.original_pos / fps_config.original_size * mask_size.0;
-                        // continue fps motion
-                        fps_config.ignore_fps_motion = false;
-                    }
-                }
+                        sensitivity,
+                    },
+                );
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                active_map.release(
+                    action.as_ref(),
+                    &cs_tx_res,
+                    mask_size.0,
+                    &mut fps_config,
+                    &mut cursor_pos,
+                );
             }
         }
     }
@@ -0,0 +1,213 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{
+    ecs::system::{Res, ResMut},
+    math::Vec2,
+};
+use bevy_ineffable::prelude::*;
+use bevy_tokio_tasks::TokioTasksRuntime;
+use rust_i18n::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::{
+    mask::mapping::{
+        binding::{ButtonBinding, ValidateMappingConfig},
+        config::ActiveMappingConfig,
+        utils::{ControlMsgHelper, Easing, MIN_MOVE_STEP_INTERVAL, Position},
+    },
+    scrcpy::constant::MotionEventAction,
+    utils::ChannelSenderCS,
+};
+
+/// How the fingers move around the center `position`.
+/// Angles are in degrees, clockwise on screen, 0 pointing right.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, JsonSchema)]
+#[serde(tag = "type")]
+pub enum GestureKind {
+    /// fingers move from `start_radius` to `end_radius`, zoom in if it grows
+    Pinch {
+        start_radius: f32,
+        end_radius: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+    /// fingers on a circle of `radius` turn by `angle`
+    Rotate {
+        radius: f32,
+        #[serde(default)]
+        start_angle: f32,
+        angle: f32,
+    },
+    /// fingers in a row across the move, `spacing` apart, all move by the offset
+    Swipe {
+        offset_x: f32,
+        offset_y: f32,
+        spacing: f32,
+    },
+}
+
+impl GestureKind {
+    /// Positions of `fingers` fingers at the eased progress `t`.
+    fn finger_positions(&self, center: Vec2, fingers: usize, t: f32) -> Vec<Vec2> {
+        let on_circle = |radius: f32, angle: f32| -> Vec<Vec2> {
+            (0..fingers)
+                .map(|i| {
+                    let a = angle.to_radians() + TAU * i as f32 / fingers as f32;
+                    center + Vec2::from_angle(a) * radius
+                })
+                .collect()
+        };
+        match *self {
+            GestureKind::Pinch {
+                start_radius,
+                end_radius,
+                angle,
+            } => on_circle(start_radius + (end_radius - start_radius) * t, angle),
+            GestureKind::Rotate {
+                radius,
+                start_angle,
+                angle,
+            } => on_circle(radius, start_angle + angle * t),
+            GestureKind::Swipe {
+                offset_x,
+                offset_y,
+                spacing,
+            } => {
+                let offset = Vec2::new(offset_x, offset_y);
+                let across = offset.try_normalize().unwrap_or(Vec2::X).perp();
+                (0..fingers)
+                    .map(|i| {
+                        let shift = (i as f32 - (fingers - 1) as f32 / 2.) * spacing;
+                        center + across * shift + offset * t
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BindMappingGesture {
+    pub note: String,
+    pub pointer_ids: Vec<u64>,
+    pub position: Position,
+    pub gesture: GestureKind,
+    pub duration: u64,
+    pub easing: Easing,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<MappingGesture> for BindMappingGesture {
+    fn from(value: MappingGesture) -> Self {
+        Self {
+            note: value.note,
+            pointer_ids: value.pointer_ids,
+            position: value.position,
+            gesture: value.gesture,
+            duration: value.duration,
+            easing: value.easing,
+            bind: value.bind.clone(),
+            input_binding: PulseBinding::just_pressed(value.bind).0,
+        }
+    }
+}
+
+/// Touch with a finger for each pointer id at once, such as pinch to zoom.
+/// Bind it to `ScrollUp` or `ScrollDown` to trigger it with the mouse wheel.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct MappingGesture {
+    pub note: String,
+    pub pointer_ids: Vec<u64>,
+    pub position: Position,
+    pub gesture: GestureKind,
+    pub duration: u64,
+    #[serde(default)]
+    pub easing: Easing,
+    pub bind: ButtonBinding,
+}
+
+impl ValidateMappingConfig for MappingGesture {
+    fn validate(&self) -> Result<(), String> {
+        if self.pointer_ids.len() < 2 {
+            return Err(t!("mask.mapping.gestureTooFewPointers").to_string());
+        }
+        for (i, pointer_id) in self.pointer_ids.iter().enumerate() {
+            if self.pointer_ids[..i].contains(pointer_id) {
+                return Err(t!(
+                    "mask.mapping.gesturePointerDuplicated",
+                    pointer_id => pointer_id
+                )
+                .to_string());
+            }
+        }
+        if let GestureKind::Pinch {
+            start_radius,
+            end_radius,
+            ..
+        } = self.gesture
+        {
+            if start_radius <= 0. || end_radius <= 0. {
+                return Err(t!("mask.mapping.gestureInvalidRadius").to_string());
+            }
+        }
+        if let GestureKind::Rotate { radius, .. } = self.gesture {
+            if radius <= 0. {
+                return Err(t!("mask.mapping.gestureInvalidRadius").to_string());
+            }
+        }
+        self.easing.validate()
+    }
+}
+
+pub fn handle_gesture(
+    ineffable: Res<Ineffable>,
+    active_mapping: Res<ActiveMappingConfig>,
+    cs_tx_res: Res<ChannelSenderCS>,
+    runtime: ResMut<TokioTasksRuntime>,
+) {
+    if let Some(active_mapping) = &active_mapping.0 {
+        for (action, mapping) in &active_mapping.index.gesture {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                let cs_tx = cs_tx_res.0.clone();
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let center: Vec2 = mapping.position.into();
+                let pointer_ids = mapping.pointer_ids.clone();
+                let gesture = mapping.gesture;
+                let duration = mapping.duration;
+                let easing = mapping.easing;
+                runtime.spawn_background_task(move |_ctx| async move {
+                    let fingers = pointer_ids.len();
+                    let send_all = |action: MotionEventAction, positions: &[Vec2]| {
+                        for (pointer_id, pos) in pointer_ids.iter().zip(positions) {
+                            ControlMsgHelper::send_touch(
+                                &cs_tx,
+                                action,
+                                *pointer_id,
+                                original_size,
+                                *pos,
+                            );
+                        }
+                    };
+
+                    let mut positions = gesture.finger_positions(center, fingers, 0.);
+                    send_all(MotionEventAction::Down, &positions);
+
+                    let steps = std::cmp::max(1, duration / MIN_MOVE_STEP_INTERVAL);
+                    let step_duration = duration / steps;
+                    for step in 1..=steps {
+                        let linear_t = step as f32 / steps as f32;
+                        positions =
+                            gesture.finger_positions(center, fingers, easing.apply(linear_t));
+                        send_all(MotionEventAction::Move, &positions);
+                        sleep(Duration::from_millis(step_duration)).await;
+                    }
+
+                    send_all(MotionEventAction::Up, &positions);
+                });
+            }
+        }
+    }
+}
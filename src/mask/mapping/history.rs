@@ -0,0 +1,342 @@
+use std::{
+    fs::{
+        File, OpenOptions, create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file,
+        rename,
+    },
+    io::{self, ErrorKind},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rust_i18n::t;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    mask::mapping::{
+        binding::ButtonBinding,
+        config::{MappingConfig, MappingType, parse_mapping_value},
+        layer::{LayerMode, MappingLayer},
+        utils::Size,
+    },
+    utils::relate_to_data_path,
+};
+
+/// Number of revisions kept for each mapping file, older ones are removed.
+pub const MAX_REVISIONS: usize = 30;
+
+/// Id of the current content of a mapping file, which can be used like a revision.
+pub const CURRENT_REVISION: &str = "current";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    pub id: String,
+    // milliseconds since unix epoch
+    pub time: u64,
+}
+
+fn history_dir(file_name: &str) -> PathBuf {
+    relate_to_data_path(["mapping_history", file_name])
+}
+
+// ids are timestamps, which also keeps them from escaping the history directory
+fn revision_path(file_name: &str, id: &str) -> Result<PathBuf, String> {
+    id.parse::<u64>()
+        .map(|time| history_dir(file_name).join(format!("{}.json", time)))
+        .map_err(|_| format!("{}: {}", t!("mask.mapping.revisionNotFound"), id))
+}
+
+/// Keep the current content of a mapping file as a revision, before it is overwritten.
+/// Returns `None` if the file doesn't exist yet.
+pub fn save_revision(file_name: &str) -> Result<Option<Revision>, String> {
+    let path = relate_to_data_path(["mapping", file_name]);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let dir = history_dir(file_name);
+    create_dir_all(&dir)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotSaveRevision"), e))?;
+    let mut source = File::open(&path)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotSaveRevision"), e))?;
+    let mut time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    // never replace a revision saved in the same millisecond
+    let mut target = loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(revision_path(file_name, &time.to_string())?)
+        {
+            Ok(file) => break file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => time += 1,
+            Err(e) => return Err(format!("{}: {}", t!("mask.mapping.cannotSaveRevision"), e)),
+        }
+    };
+    io::copy(&mut source, &mut target)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotSaveRevision"), e))?;
+    let revision = Revision {
+        id: time.to_string(),
+        time,
+    };
+
+    for old in list_revisions(file_name)?.iter().skip(MAX_REVISIONS) {
+        if let Ok(old_path) = revision_path(file_name, &old.id) {
+            remove_file(old_path).ok();
+        }
+    }
+    Ok(Some(revision))
+}
+
+/// Move the revisions along with a renamed mapping file.
+pub fn rename_history(file_name: &str, new_file_name: &str) -> Result<(), String> {
+    let dir = history_dir(file_name);
+    if !dir.exists() {
+        return Ok(());
+    }
+    let new_dir = history_dir(new_file_name);
+    // left over from an older file of the same name
+    if new_dir.exists() {
+        remove_dir_all(&new_dir)
+            .map_err(|e| format!("{}: {}", t!("mask.mapping.cannotMoveRevisions"), e))?;
+    }
+    rename(dir, new_dir).map_err(|e| format!("{}: {}", t!("mask.mapping.cannotMoveRevisions"), e))
+}
+
+/// Remove all revisions of a deleted mapping file.
+pub fn remove_history(file_name: &str) -> Result<(), String> {
+    let dir = history_dir(file_name);
+    if !dir.exists() {
+        return Ok(());
+    }
+    remove_dir_all(dir).map_err(|e| format!("{}: {}", t!("mask.mapping.cannotRemoveRevisions"), e))
+}
+
+/// Revisions of a mapping file, newest first.
+pub fn list_revisions(file_name: &str) -> Result<Vec<Revision>, String> {
+    let dir = history_dir(file_name);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries =
+        read_dir(dir).map_err(|e| format!("{}: {}", t!("mask.mapping.cannotReadRevisions"), e))?;
+
+    let mut revisions: Vec<Revision> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                return None;
+            }
+            let time = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+            Some(Revision {
+                id: time.to_string(),
+                time,
+            })
+        })
+        .collect();
+    revisions.sort_by(|a, b| b.time.cmp(&a.time));
+    Ok(revisions)
+}
+
+/// Read a revision, or the current file if `id` is `CURRENT_REVISION`.
+/// Revisions of an older schema are upgraded in memory only.
+pub fn read_revision(file_name: &str, id: &str) -> Result<MappingConfig, String> {
+    let path = if id == CURRENT_REVISION {
+        relate_to_data_path(["mapping", file_name])
+    } else {
+        revision_path(file_name, id)?
+    };
+    if !path.exists() {
+        return Err(format!("{}: {}", t!("mask.mapping.revisionNotFound"), id));
+    }
+
+    let config_string = read_to_string(path)
+        .map_err(|e| format!("{}: {}", t!("web.mapping.cannotReadMappingConfig"), e))?;
+    let value: Value = serde_json::from_str(&config_string)
+        .map_err(|e| format!("{}: {}", t!("web.mapping.cannotDeserializeConfig"), e))?;
+    parse_mapping_value(value).map(|(config, _)| config)
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Difference of a mapping entry in the base list or in the mappings of `layer`.
+/// Indexes are the positions of the entry in the `from` and `to` lists.
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingChange {
+    pub layer: Option<String>,
+    pub before_index: Option<usize>,
+    pub after_index: Option<usize>,
+    pub change: ChangeKind,
+    pub before: Option<MappingType>,
+    pub after: Option<MappingType>,
+}
+
+/// Difference of the settings of a layer, its mappings are compared separately.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerChange {
+    pub name: String,
+    pub change: ChangeKind,
+    pub before: Option<LayerSettings>,
+    pub after: Option<LayerSettings>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerSettings {
+    pub mode: LayerMode,
+    pub bind: ButtonBinding,
+}
+
+impl From<&MappingLayer> for LayerSettings {
+    fn from(layer: &MappingLayer) -> Self {
+        Self {
+            mode: layer.mode,
+            bind: layer.bind.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SizeChange {
+    pub before: Size,
+    pub after: Size,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MappingConfigDiff {
+    pub original_size: Option<SizeChange>,
+    pub layers: Vec<LayerChange>,
+    pub mappings: Vec<MappingChange>,
+}
+
+fn same_value<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+// entries with the same type, keys and pointers are taken as the same mapping
+fn mapping_key(mapping: &MappingType) -> (&'static str, Vec<String>, Vec<u64>) {
+    (mapping.into(), mapping.bindings(), mapping.pointer_ids())
+}
+
+fn diff_mapping_list(
+    layer: Option<&str>,
+    before: &[MappingType],
+    after: &[MappingType],
+    changes: &mut Vec<MappingChange>,
+) {
+    let mut push = |before_index: Option<usize>, after_index: Option<usize>, change| {
+        changes.push(MappingChange {
+            layer: layer.map(|layer| layer.to_string()),
+            before_index,
+            after_index,
+            change,
+            before: before_index.map(|i| before[i].clone()),
+            after: after_index.map(|i| after[i].clone()),
+        })
+    };
+
+    // matched by type, keys and pointers first, then edited entries by type in order
+    let passes: [fn(&MappingType, &MappingType) -> bool; 2] = [
+        |b, a| mapping_key(b) == mapping_key(a),
+        |b, a| <&str>::from(b) == <&str>::from(a),
+    ];
+    let mut matched = vec![false; before.len()];
+    let mut pairs: Vec<Option<usize>> = vec![None; after.len()];
+    for same in passes {
+        for (after_index, mapping) in after.iter().enumerate() {
+            if pairs[after_index].is_some() {
+                continue;
+            }
+            if let Some(before_index) =
+                (0..before.len()).find(|&i| !matched[i] && same(&before[i], mapping))
+            {
+                matched[before_index] = true;
+                pairs[after_index] = Some(before_index);
+            }
+        }
+    }
+
+    for (after_index, pair) in pairs.into_iter().enumerate() {
+        match pair {
+            Some(before_index) => {
+                if !same_value(&before[before_index], &after[after_index]) {
+                    push(Some(before_index), Some(after_index), ChangeKind::Modified);
+                }
+            }
+            None => push(None, Some(after_index), ChangeKind::Added),
+        }
+    }
+    for before_index in (0..before.len()).filter(|&i| !matched[i]) {
+        push(Some(before_index), None, ChangeKind::Removed);
+    }
+}
+
+/// Compare two configs. Mapping entries are matched by their type, keys and pointers,
+/// layers by name.
+pub fn diff_mapping_configs(before: &MappingConfig, after: &MappingConfig) -> MappingConfigDiff {
+    let mut diff = MappingConfigDiff::default();
+    if before.original_size != after.original_size {
+        diff.original_size = Some(SizeChange {
+            before: before.original_size,
+            after: after.original_size,
+        });
+    }
+    diff_mapping_list(None, &before.mappings, &after.mappings, &mut diff.mappings);
+
+    let mut layer_names: Vec<&str> = before
+        .layers
+        .iter()
+        .map(|layer| layer.name.as_str())
+        .collect();
+    for layer in after.layers.iter() {
+        if !layer_names.contains(&layer.name.as_str()) {
+            layer_names.push(&layer.name);
+        }
+    }
+    for name in layer_names {
+        let find = |config: &MappingConfig| -> Option<MappingLayer> {
+            config
+                .layers
+                .iter()
+                .find(|layer| layer.name == name)
+                .cloned()
+        };
+        let (before_layer, after_layer) = (find(before), find(after));
+        let (before_settings, after_settings) = (
+            before_layer.as_ref().map(LayerSettings::from),
+            after_layer.as_ref().map(LayerSettings::from),
+        );
+        let change = match (&before_settings, &after_settings) {
+            (Some(b), Some(a)) => (!same_value(b, a)).then_some(ChangeKind::Modified),
+            (None, Some(_)) => Some(ChangeKind::Added),
+            (Some(_), None) => Some(ChangeKind::Removed),
+            (None, None) => None,
+        };
+        if let Some(change) = change {
+            diff.layers.push(LayerChange {
+                name: name.to_string(),
+                change,
+                before: before_settings,
+                after: after_settings,
+            });
+        }
+
+        let mappings = |layer: Option<MappingLayer>| -> Vec<MappingType> {
+            layer.map(|layer| layer.mappings).unwrap_or_default()
+        };
+        diff_mapping_list(
+            Some(name),
+            &mappings(before_layer),
+            &mappings(after_layer),
+            &mut diff.mappings,
+        );
+    }
+    diff
+}
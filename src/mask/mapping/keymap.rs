@@ -0,0 +1,1399 @@
+//! Conversion between `MappingConfig` and the keymaps of other tools.
+//!
+//! Only the mapping types that have a counterpart are converted,
+//! everything else is reported as a `SkippedItem`.
+
+use bevy::math::Vec2;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::mask::mapping::{
+    binding::{ButtonBinding, DirectionBinding, MergedButton},
+    config::{MappingConfig, MappingType, default_mapping_config},
+    direction_pad::{MappingDirectionPad, StickResponse},
+    fire::{AimAcceleration, FpsRecenter, MappingFps},
+    observation::MappingObservation,
+    swipe::MappingSwipe,
+    tap::{MappingMultipleTap, MappingMultipleTapItem, MappingSingleTap},
+    utils::{Anchor, Easing, Position, Size},
+};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum KeymapFormat {
+    /// keymap json of QtScrcpy, positions are fractions of the screen size
+    QtScrcpy,
+    /// keymap json of scrcpy-mask before the rewrite, positions are pixels of `relativeSize`
+    ScrcpyMaskLegacy,
+}
+
+/// An entry which is not converted, or only partly.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedItem {
+    pub item: String,
+    pub reason: String,
+}
+
+impl SkippedItem {
+    fn new(item: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            item: item.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+const TAP_DURATION: u64 = 50; // ms
+const CLICK_TWICE_WAIT: u64 = 50; // ms
+// interval between the two positions of a drag with `dragSpeed` 1
+const DRAG_INTERVAL: u64 = 100; // ms
+
+// Qt key names which don't follow the `Key_A` / `Key_1` / `Key_F1` rules.
+// The first entry of a button is used for export.
+const QT_KEY_NAMES: &[(&str, &str)] = &[
+    ("LeftButton", "M-Left"),
+    ("RightButton", "M-Right"),
+    ("MidButton", "M-Middle"),
+    ("MiddleButton", "M-Middle"),
+    ("BackButton", "M-Back"),
+    ("ForwardButton", "M-Forward"),
+    ("WheelUp", "ScrollUp"),
+    ("WheelDown", "ScrollDown"),
+    ("Key_QuoteLeft", "Backquote"),
+    ("Key_Apostrophe", "Quote"),
+    ("Key_Minus", "Minus"),
+    ("Key_Equal", "Equal"),
+    ("Key_BracketLeft", "BracketLeft"),
+    ("Key_BracketRight", "BracketRight"),
+    ("Key_Backslash", "Backslash"),
+    ("Key_Semicolon", "Semicolon"),
+    ("Key_Comma", "Comma"),
+    ("Key_Period", "Period"),
+    ("Key_Slash", "Slash"),
+    ("Key_Space", "Space"),
+    ("Key_Tab", "Tab"),
+    ("Key_Return", "Enter"),
+    ("Key_Enter", "NumpadEnter"),
+    ("Key_Backspace", "Backspace"),
+    ("Key_Escape", "Escape"),
+    ("Key_CapsLock", "CapsLock"),
+    ("Key_Shift", "ShiftLeft"),
+    ("Key_Control", "ControlLeft"),
+    ("Key_Alt", "AltLeft"),
+    ("Key_Meta", "SuperLeft"),
+    ("Key_Up", "ArrowUp"),
+    ("Key_Down", "ArrowDown"),
+    ("Key_Left", "ArrowLeft"),
+    ("Key_Right", "ArrowRight"),
+    ("Key_Insert", "Insert"),
+    ("Key_Delete", "Delete"),
+    ("Key_Home", "Home"),
+    ("Key_End", "End"),
+    ("Key_PageUp", "PageUp"),
+    ("Key_PageDown", "PageDown"),
+];
+
+fn unsupported_key(key: &str) -> String {
+    format!("{}: {}", t!("mask.mapping.keymapUnsupportedKey"), key)
+}
+
+// F1 to F35 have the same name in both
+fn is_function_key(name: &str) -> bool {
+    name.strip_prefix('F')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn from_qt_key(key: &str) -> Result<ButtonBinding, String> {
+    let name = if let Some((_, name)) = QT_KEY_NAMES.iter().find(|(qt, _)| *qt == key) {
+        name.to_string()
+    } else {
+        let rest = key
+            .strip_prefix("Key_")
+            .ok_or_else(|| unsupported_key(key))?;
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_uppercase() => format!("Key{}", c),
+            (Some(c), None) if c.is_ascii_digit() => format!("Digit{}", c),
+            (Some('F'), Some(_)) if is_function_key(rest) => rest.to_string(),
+            _ => return Err(unsupported_key(key)),
+        }
+    };
+    name.parse::<MergedButton>()
+        .map(|button| ButtonBinding::new(vec![button]))
+        .map_err(|_| unsupported_key(key))
+}
+
+fn to_qt_key(binding: &ButtonBinding) -> Result<String, String> {
+    let [button] = binding.buttons() else {
+        return Err(t!("mask.mapping.keymapChordNotSupported").to_string());
+    };
+    let name = button.to_string();
+    if let Some((qt, _)) = QT_KEY_NAMES.iter().find(|(_, ours)| *ours == name) {
+        return Ok(qt.to_string());
+    }
+    if let Some(c) = name.strip_prefix("Key").filter(|c| c.len() == 1) {
+        return Ok(format!("Key_{}", c));
+    }
+    if let Some(c) = name.strip_prefix("Digit") {
+        return Ok(format!("Key_{}", c));
+    }
+    if is_function_key(&name) {
+        return Ok(format!("Key_{}", name));
+    }
+    Err(unsupported_key(&name))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct QtPos {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct QtClickNode {
+    delay: u64,
+    pos: QtPos,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+enum QtKeyMapNode {
+    #[serde(rename = "KMT_CLICK")]
+    Click {
+        #[serde(default)]
+        comment: String,
+        key: String,
+        pos: QtPos,
+        #[serde(default)]
+        switch_map: bool,
+    },
+    #[serde(rename = "KMT_CLICK_TWICE")]
+    ClickTwice {
+        #[serde(default)]
+        comment: String,
+        key: String,
+        pos: QtPos,
+    },
+    #[serde(rename = "KMT_CLICK_MULTI")]
+    ClickMulti {
+        #[serde(default)]
+        comment: String,
+        key: String,
+        click_nodes: Vec<QtClickNode>,
+    },
+    #[serde(rename = "KMT_DRAG")]
+    Drag {
+        #[serde(default)]
+        comment: String,
+        key: String,
+        start_pos: QtPos,
+        end_pos: QtPos,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        drag_speed: Option<f32>,
+    },
+    #[serde(rename = "KMT_STEER_WHEEL")]
+    SteerWheel {
+        #[serde(default)]
+        comment: String,
+        center_pos: QtPos,
+        left_offset: f32,
+        right_offset: f32,
+        up_offset: f32,
+        down_offset: f32,
+        left_key: String,
+        right_key: String,
+        up_key: String,
+        down_key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct QtSmallEyes {
+    #[serde(default)]
+    comment: String,
+    #[serde(rename = "type", default = "qt_click_type")]
+    node_type: String,
+    key: String,
+    pos: QtPos,
+    #[serde(default)]
+    switch_map: bool,
+}
+
+fn qt_click_type() -> String {
+    "KMT_CLICK".to_string()
+}
+
+fn qt_switch_key() -> String {
+    "Key_QuoteLeft".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct QtMouseMoveMap {
+    start_pos: QtPos,
+    // older keymaps use a single `speedRatio`
+    #[serde(default, skip_serializing)]
+    speed_ratio: Option<f32>,
+    #[serde(default)]
+    speed_ratio_x: Option<f32>,
+    #[serde(default)]
+    speed_ratio_y: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    small_eyes: Option<QtSmallEyes>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct QtKeyMap {
+    #[serde(default = "qt_switch_key")]
+    switch_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mouse_move_map: Option<QtMouseMoveMap>,
+    // kept as values, so that one unknown node doesn't fail the whole keymap
+    #[serde(default)]
+    key_map_nodes: Vec<Value>,
+}
+
+// QtScrcpy divides mouse movement by its speed ratio, while it is multiplied by the sensitivity here
+fn reciprocal(value: f32) -> f32 {
+    if value > 0. { 1. / value } else { 1. }
+}
+
+struct Importer {
+    config: MappingConfig,
+    skipped: Vec<SkippedItem>,
+    next_pointer_id: u64,
+}
+
+impl Importer {
+    fn new(original_size: Size) -> Self {
+        let mut config = default_mapping_config();
+        config.original_size = original_size;
+        Self {
+            config,
+            skipped: Vec::new(),
+            next_pointer_id: 1,
+        }
+    }
+
+    fn pointer_id(&mut self) -> u64 {
+        let id = self.next_pointer_id;
+        self.next_pointer_id += 1;
+        id
+    }
+
+    fn position(&self, pos: QtPos) -> Position {
+        Position {
+            x: (pos.x * self.config.original_size.width as f32).round(),
+            y: (pos.y * self.config.original_size.height as f32).round(),
+            anchor: Anchor::Absolute,
+        }
+    }
+
+    fn import_qt_node(&mut self, node: QtKeyMapNode) -> Result<MappingType, String> {
+        Ok(match node {
+            QtKeyMapNode::Click {
+                comment, key, pos, ..
+            } => MappingType::SingleTap(MappingSingleTap {
+                position: self.position(pos),
+                note: comment,
+                pointer_id: self.pointer_id(),
+                duration: TAP_DURATION,
+                // QtScrcpy holds the touch as long as the key is pressed
+                sync: true,
+                humanize: None,
+                bind: from_qt_key(&key)?,
+            }),
+            QtKeyMapNode::ClickTwice { comment, key, pos } => {
+                let position = self.position(pos);
+                MappingType::MultipleTap(MappingMultipleTap {
+                    note: comment,
+                    pointer_id: self.pointer_id(),
+                    items: vec![
+                        MappingMultipleTapItem {
+                            position,
+                            duration: TAP_DURATION,
+                            wait: 0,
+                        },
+                        MappingMultipleTapItem {
+                            position,
+                            duration: TAP_DURATION,
+                            wait: CLICK_TWICE_WAIT,
+                        },
+                    ],
+                    humanize: None,
+                    bind: from_qt_key(&key)?,
+                })
+            }
+            QtKeyMapNode::ClickMulti {
+                comment,
+                key,
+                click_nodes,
+            } => MappingType::MultipleTap(MappingMultipleTap {
+                note: comment,
+                pointer_id: self.pointer_id(),
+                items: click_nodes
+                    .into_iter()
+                    .map(|node| MappingMultipleTapItem {
+                        position: self.position(node.pos),
+                        duration: TAP_DURATION,
+                        wait: node.delay,
+                    })
+                    .collect(),
+                humanize: None,
+                bind: from_qt_key(&key)?,
+            }),
+            QtKeyMapNode::Drag {
+                comment,
+                key,
+                start_pos,
+                end_pos,
+                drag_speed,
+            } => {
+                let speed = drag_speed.unwrap_or(1.).clamp(0.1, 1.);
+                MappingType::Swipe(MappingSwipe {
+                    note: comment,
+                    pointer_id: self.pointer_id(),
+                    positions: vec![self.position(start_pos), self.position(end_pos)],
+                    interval: (DRAG_INTERVAL as f32 / speed).round() as u64,
+                    easing: Easing::default(),
+                    segments: Vec::new(),
+                    hold: 0,
+                    humanize: None,
+                    bind: from_qt_key(&key)?,
+                })
+            }
+            QtKeyMapNode::SteerWheel {
+                comment,
+                center_pos,
+                left_offset,
+                right_offset,
+                up_offset,
+                down_offset,
+                left_key,
+                right_key,
+                up_key,
+                down_key,
+            } => {
+                let size: Vec2 = self.config.original_size.into();
+                MappingType::DirectionPad(MappingDirectionPad {
+                    note: comment,
+                    pointer_id: self.pointer_id(),
+                    position: self.position(center_pos),
+                    initial_duration: 0,
+                    max_offset_x: (left_offset.max(right_offset) * size.x).round(),
+                    max_offset_y: (up_offset.max(down_offset) * size.y).round(),
+                    stick: StickResponse::default(),
+                    modifiers: vec![],
+                    bind: DirectionBinding::Button {
+                        up: from_qt_key(&up_key)?,
+                        down: from_qt_key(&down_key)?,
+                        left: from_qt_key(&left_key)?,
+                        right: from_qt_key(&right_key)?,
+                    },
+                })
+            }
+        })
+    }
+
+    fn import_qt(mut self, keymap: QtKeyMap) -> (MappingConfig, Vec<SkippedItem>) {
+        for (index, node) in keymap.key_map_nodes.into_iter().enumerate() {
+            let node_type = node
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let item = format!("keyMapNodes#{} ({})", index, node_type);
+            let result = serde_json::from_value::<QtKeyMapNode>(node)
+                .map_err(|e| format!("{}: {}", t!("mask.mapping.keymapUnsupportedType"), e))
+                .and_then(|node| {
+                    if let QtKeyMapNode::SteerWheel {
+                        left_offset,
+                        right_offset,
+                        up_offset,
+                        down_offset,
+                        ..
+                    } = &node
+                    {
+                        if left_offset != right_offset || up_offset != down_offset {
+                            self.skipped.push(SkippedItem::new(
+                                item.clone(),
+                                t!("mask.mapping.keymapOffsetApproximated"),
+                            ));
+                        }
+                    }
+                    self.import_qt_node(node)
+                });
+            match result {
+                Ok(mapping) => self.config.mappings.push(mapping),
+                Err(e) => self.skipped.push(SkippedItem::new(item, e)),
+            }
+        }
+
+        if let Some(mouse_move_map) = keymap.mouse_move_map {
+            let ratio = mouse_move_map.speed_ratio.unwrap_or(1.);
+            let sensitivity_x = reciprocal(mouse_move_map.speed_ratio_x.unwrap_or(ratio));
+            let sensitivity_y = reciprocal(mouse_move_map.speed_ratio_y.unwrap_or(ratio));
+            match from_qt_key(&keymap.switch_key) {
+                Ok(bind) => {
+                    let fps = MappingFps {
+                        note: String::new(),
+                        pointer_id: self.pointer_id(),
+                        position: self.position(mouse_move_map.start_pos),
+                        sensitivity_x,
+                        sensitivity_y,
+                        acceleration: AimAcceleration::None,
+                        ads: None,
+                        presets: vec![],
+                        recenter: FpsRecenter::default(),
+                        bind,
+                    };
+                    self.config.mappings.push(MappingType::Fps(fps));
+                }
+                Err(e) => self.skipped.push(SkippedItem::new("mouseMoveMap", e)),
+            }
+
+            if let Some(small_eyes) = mouse_move_map.small_eyes {
+                match from_qt_key(&small_eyes.key) {
+                    Ok(bind) => {
+                        let observation = MappingObservation {
+                            note: small_eyes.comment,
+                            pointer_id: self.pointer_id(),
+                            position: self.position(small_eyes.pos),
+                            sensitivity_x,
+                            sensitivity_y,
+                            bind,
+                        };
+                        self.config
+                            .mappings
+                            .push(MappingType::Observation(observation));
+                    }
+                    Err(e) => self
+                        .skipped
+                        .push(SkippedItem::new("mouseMoveMap.smallEyes", e)),
+                }
+            }
+        }
+
+        (self.config, self.skipped)
+    }
+
+    fn import_legacy(mut self, keymap: LegacyKeyMap) -> (MappingConfig, Vec<SkippedItem>) {
+        for (index, item) in keymap.list.into_iter().enumerate() {
+            let item_type = item
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let name = format!("list#{} ({})", index, item_type);
+            let result = serde_json::from_value::<LegacyKeyMapping>(item)
+                .map_err(|e| format!("{}: {}", t!("mask.mapping.keymapUnsupportedType"), e))
+                .and_then(|item| item.into_mapping());
+            match result {
+                Ok(mapping) => self.config.mappings.push(mapping),
+                Err(e) => self.skipped.push(SkippedItem::new(name, e)),
+            }
+        }
+        (self.config, self.skipped)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct LegacySize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct LegacyPos {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LegacyDirectionKeys {
+    left: String,
+    right: String,
+    up: String,
+    down: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+enum LegacyKeyMapping {
+    Tap {
+        #[serde(default)]
+        note: String,
+        pointer_id: u64,
+        pos_x: f32,
+        pos_y: f32,
+        key: String,
+        time: u64,
+    },
+    SteeringWheel {
+        #[serde(default)]
+        note: String,
+        pointer_id: u64,
+        pos_x: f32,
+        pos_y: f32,
+        key: LegacyDirectionKeys,
+        offset: f32,
+    },
+    Swipe {
+        #[serde(default)]
+        note: String,
+        pointer_id: u64,
+        key: String,
+        pos: Vec<LegacyPos>,
+        interval_between_pos: u64,
+    },
+    Sight {
+        #[serde(default)]
+        note: String,
+        pointer_id: u64,
+        pos_x: f32,
+        pos_y: f32,
+        key: String,
+        scale_x: f32,
+        scale_y: f32,
+    },
+    Observation {
+        #[serde(default)]
+        note: String,
+        pointer_id: u64,
+        pos_x: f32,
+        pos_y: f32,
+        key: String,
+        scale: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LegacyKeyMap {
+    relative_size: LegacySize,
+    #[serde(default)]
+    list: Vec<Value>,
+}
+
+// key names are the same as ours, except for mouse buttons
+const LEGACY_MOUSE_KEYS: [(&str, &str); 5] = [
+    ("M0", "M-Left"),
+    ("M1", "M-Middle"),
+    ("M2", "M-Right"),
+    ("M3", "M-Back"),
+    ("M4", "M-Forward"),
+];
+
+fn from_legacy_key(key: &str) -> Result<ButtonBinding, String> {
+    let name = LEGACY_MOUSE_KEYS
+        .iter()
+        .find(|(legacy, _)| *legacy == key)
+        .map_or(key, |(_, ours)| ours);
+    name.parse::<MergedButton>()
+        .map(|button| ButtonBinding::new(vec![button]))
+        .map_err(|_| unsupported_key(key))
+}
+
+fn to_legacy_key(binding: &ButtonBinding) -> Result<String, String> {
+    let [button] = binding.buttons() else {
+        return Err(t!("mask.mapping.keymapChordNotSupported").to_string());
+    };
+    let name = button.to_string();
+    Ok(LEGACY_MOUSE_KEYS
+        .iter()
+        .find(|(_, ours)| *ours == name)
+        .map_or(name, |(legacy, _)| legacy.to_string()))
+}
+
+impl LegacyKeyMapping {
+    fn into_mapping(self) -> Result<MappingType, String> {
+        let position = |x: f32, y: f32| Position {
+            x,
+            y,
+            anchor: Anchor::Absolute,
+        };
+        Ok(match self {
+            LegacyKeyMapping::Tap {
+                note,
+                pointer_id,
+                pos_x,
+                pos_y,
+                key,
+                time,
+            } => MappingType::SingleTap(MappingSingleTap {
+                position: position(pos_x, pos_y),
+                note,
+                pointer_id,
+                duration: time,
+                sync: false,
+                humanize: None,
+                bind: from_legacy_key(&key)?,
+            }),
+            LegacyKeyMapping::SteeringWheel {
+                note,
+                pointer_id,
+                pos_x,
+                pos_y,
+                key,
+                offset,
+            } => MappingType::DirectionPad(MappingDirectionPad {
+                note,
+                pointer_id,
+                position: position(pos_x, pos_y),
+                initial_duration: 0,
+                max_offset_x: offset,
+                max_offset_y: offset,
+                stick: StickResponse::default(),
+                modifiers: vec![],
+                bind: DirectionBinding::Button {
+                    up: from_legacy_key(&key.up)?,
+                    down: from_legacy_key(&key.down)?,
+                    left: from_legacy_key(&key.left)?,
+                    right: from_legacy_key(&key.right)?,
+                },
+            }),
+            LegacyKeyMapping::Swipe {
+                note,
+                pointer_id,
+                key,
+                pos,
+                interval_between_pos,
+            } => MappingType::Swipe(MappingSwipe {
+                note,
+                pointer_id,
+                positions: pos.into_iter().map(|p| position(p.x, p.y)).collect(),
+                interval: interval_between_pos,
+                easing: Easing::default(),
+                segments: Vec::new(),
+                hold: 0,
+                humanize: None,
+                bind: from_legacy_key(&key)?,
+            }),
+            LegacyKeyMapping::Sight {
+                note,
+                pointer_id,
+                pos_x,
+                pos_y,
+                key,
+                scale_x,
+                scale_y,
+            } => MappingType::Fps(MappingFps {
+                note,
+                pointer_id,
+                position: position(pos_x, pos_y),
+                sensitivity_x: scale_x,
+                sensitivity_y: scale_y,
+                acceleration: AimAcceleration::None,
+                ads: None,
+                presets: vec![],
+                recenter: FpsRecenter::default(),
+                bind: from_legacy_key(&key)?,
+            }),
+            LegacyKeyMapping::Observation {
+                note,
+                pointer_id,
+                pos_x,
+                pos_y,
+                key,
+                scale,
+            } => MappingType::Observation(MappingObservation {
+                note,
+                pointer_id,
+                position: position(pos_x, pos_y),
+                sensitivity_x: scale,
+                sensitivity_y: scale,
+                bind: from_legacy_key(&key)?,
+            }),
+        })
+    }
+}
+
+/// Convert a keymap of another tool into a mapping config.
+/// `original_size` is used for formats with relative positions.
+pub fn import_keymap(
+    format: KeymapFormat,
+    keymap: Value,
+    original_size: Size,
+) -> Result<(MappingConfig, Vec<SkippedItem>), String> {
+    let invalid = |e: serde_json::Error| format!("{}: {}", t!("mask.mapping.keymapInvalid"), e);
+    match format {
+        KeymapFormat::QtScrcpy => {
+            let keymap: QtKeyMap = serde_json::from_value(keymap).map_err(invalid)?;
+            Ok(Importer::new(original_size).import_qt(keymap))
+        }
+        KeymapFormat::ScrcpyMaskLegacy => {
+            let keymap: LegacyKeyMap = serde_json::from_value(keymap).map_err(invalid)?;
+            let original_size = Size {
+                width: keymap.relative_size.w,
+                height: keymap.relative_size.h,
+            };
+            Ok(Importer::new(original_size).import_legacy(keymap))
+        }
+    }
+}
+
+struct QtExporter {
+    size: Vec2,
+    keymap: QtKeyMap,
+    skipped: Vec<SkippedItem>,
+}
+
+impl QtExporter {
+    fn pos(&self, position: &Position) -> QtPos {
+        let mut position = *position;
+        position.resolve(self.size, self.size);
+        QtPos {
+            x: (position.x / self.size.x).clamp(0., 1.),
+            y: (position.y / self.size.y).clamp(0., 1.),
+        }
+    }
+
+    fn export_mapping(&mut self, item: &str, mapping: &MappingType) -> Result<(), String> {
+        let node = match mapping {
+            MappingType::SingleTap(m) => QtKeyMapNode::Click {
+                comment: m.note.clone(),
+                key: to_qt_key(&m.bind)?,
+                pos: self.pos(&m.position),
+                switch_map: false,
+            },
+            MappingType::MultipleTap(m) => match m.items.as_slice() {
+                [first, second] if first.position == second.position => QtKeyMapNode::ClickTwice {
+                    comment: m.note.clone(),
+                    key: to_qt_key(&m.bind)?,
+                    pos: self.pos(&first.position),
+                },
+                items => QtKeyMapNode::ClickMulti {
+                    comment: m.note.clone(),
+                    key: to_qt_key(&m.bind)?,
+                    click_nodes: items
+                        .iter()
+                        .map(|item| QtClickNode {
+                            delay: item.wait,
+                            pos: self.pos(&item.position),
+                        })
+                        .collect(),
+                },
+            },
+            MappingType::Swipe(m) => {
+                let (Some(first), Some(last)) = (m.positions.first(), m.positions.last()) else {
+                    return Err(t!("mask.mapping.keymapUnsupportedType").to_string());
+                };
+                if m.positions.len() > 2 {
+                    self.skipped.push(SkippedItem::new(
+                        item,
+                        t!("mask.mapping.keymapSwipeSimplified"),
+                    ));
+                }
+                let interval = m.interval.max(1) * (m.positions.len().max(2) as u64 - 1);
+                QtKeyMapNode::Drag {
+                    comment: m.note.clone(),
+                    key: to_qt_key(&m.bind)?,
+                    start_pos: self.pos(first),
+                    end_pos: self.pos(last),
+                    drag_speed: Some((DRAG_INTERVAL as f32 / interval as f32).clamp(0.1, 1.)),
+                }
+            }
+            MappingType::DirectionPad(m) => {
+                let DirectionBinding::Button {
+                    up,
+                    down,
+                    left,
+                    right,
+                } = &m.bind
+                else {
+                    return Err(t!("mask.mapping.keymapJoystickNotSupported").to_string());
+                };
+                let offset_x = m.max_offset_x / self.size.x;
+                let offset_y = m.max_offset_y / self.size.y;
+                QtKeyMapNode::SteerWheel {
+                    comment: m.note.clone(),
+                    center_pos: self.pos(&m.position),
+                    left_offset: offset_x,
+                    right_offset: offset_x,
+                    up_offset: offset_y,
+                    down_offset: offset_y,
+                    left_key: to_qt_key(left)?,
+                    right_key: to_qt_key(right)?,
+                    up_key: to_qt_key(up)?,
+                    down_key: to_qt_key(down)?,
+                }
+            }
+            MappingType::Fps(m) => {
+                if self.keymap.mouse_move_map.is_some() {
+                    return Err(t!("mask.mapping.keymapFpsDuplicated").to_string());
+                }
+                self.keymap.switch_key = to_qt_key(&m.bind)?;
+                self.keymap.mouse_move_map = Some(QtMouseMoveMap {
+                    start_pos: self.pos(&m.position),
+                    speed_ratio: None,
+                    speed_ratio_x: Some(reciprocal(m.sensitivity_x)),
+                    speed_ratio_y: Some(reciprocal(m.sensitivity_y)),
+                    small_eyes: None,
+                });
+                return Ok(());
+            }
+            _ => return Err(t!("mask.mapping.keymapUnsupportedType").to_string()),
+        };
+        self.keymap
+            .key_map_nodes
+            .push(serde_json::to_value(node).unwrap());
+        Ok(())
+    }
+
+    // small eyes belong to the mouse move map, so they are added after it
+    fn export_observation(&mut self, observation: &MappingObservation) -> Result<(), String> {
+        let key = to_qt_key(&observation.bind)?;
+        let pos = self.pos(&observation.position);
+        let Some(mouse_move_map) = &mut self.keymap.mouse_move_map else {
+            return Err(t!("mask.mapping.keymapObservationWithoutFps").to_string());
+        };
+        if mouse_move_map.small_eyes.is_some() {
+            return Err(t!("mask.mapping.keymapObservationDuplicated").to_string());
+        }
+        mouse_move_map.small_eyes = Some(QtSmallEyes {
+            comment: observation.note.clone(),
+            node_type: qt_click_type(),
+            key,
+            pos,
+            switch_map: false,
+        });
+        Ok(())
+    }
+}
+
+struct LegacyExporter {
+    size: Vec2,
+    skipped: Vec<SkippedItem>,
+}
+
+impl LegacyExporter {
+    fn pos(&self, position: &Position) -> LegacyPos {
+        let mut position = *position;
+        position.resolve(self.size, self.size);
+        LegacyPos {
+            x: position.x,
+            y: position.y,
+        }
+    }
+
+    // the format has a single value for both axes
+    fn single_value(&mut self, item: &str, x: f32, y: f32) -> f32 {
+        if x != y {
+            self.skipped.push(SkippedItem::new(
+                item,
+                t!("mask.mapping.keymapAxisSimplified"),
+            ));
+        }
+        x
+    }
+
+    fn export_mapping(&mut self, item: &str, mapping: &MappingType) -> Result<Value, String> {
+        let node = match mapping {
+            MappingType::SingleTap(m) => {
+                let pos = self.pos(&m.position);
+                LegacyKeyMapping::Tap {
+                    note: m.note.clone(),
+                    pointer_id: m.pointer_id,
+                    pos_x: pos.x,
+                    pos_y: pos.y,
+                    key: to_legacy_key(&m.bind)?,
+                    time: m.duration,
+                }
+            }
+            MappingType::DirectionPad(m) => {
+                let DirectionBinding::Button {
+                    up,
+                    down,
+                    left,
+                    right,
+                } = &m.bind
+                else {
+                    return Err(t!("mask.mapping.keymapJoystickNotSupported").to_string());
+                };
+                let pos = self.pos(&m.position);
+                LegacyKeyMapping::SteeringWheel {
+                    note: m.note.clone(),
+                    pointer_id: m.pointer_id,
+                    pos_x: pos.x,
+                    pos_y: pos.y,
+                    key: LegacyDirectionKeys {
+                        left: to_legacy_key(left)?,
+                        right: to_legacy_key(right)?,
+                        up: to_legacy_key(up)?,
+                        down: to_legacy_key(down)?,
+                    },
+                    offset: self.single_value(item, m.max_offset_x, m.max_offset_y),
+                }
+            }
+            MappingType::Swipe(m) => LegacyKeyMapping::Swipe {
+                note: m.note.clone(),
+                pointer_id: m.pointer_id,
+                key: to_legacy_key(&m.bind)?,
+                pos: m.positions.iter().map(|p| self.pos(p)).collect(),
+                interval_between_pos: m.interval,
+            },
+            MappingType::Fps(m) => {
+                let pos = self.pos(&m.position);
+                LegacyKeyMapping::Sight {
+                    note: m.note.clone(),
+                    pointer_id: m.pointer_id,
+                    pos_x: pos.x,
+                    pos_y: pos.y,
+                    key: to_legacy_key(&m.bind)?,
+                    scale_x: m.sensitivity_x,
+                    scale_y: m.sensitivity_y,
+                }
+            }
+            MappingType::Observation(m) => {
+                let pos = self.pos(&m.position);
+                LegacyKeyMapping::Observation {
+                    note: m.note.clone(),
+                    pointer_id: m.pointer_id,
+                    pos_x: pos.x,
+                    pos_y: pos.y,
+                    key: to_legacy_key(&m.bind)?,
+                    scale: self.single_value(item, m.sensitivity_x, m.sensitivity_y),
+                }
+            }
+            _ => return Err(t!("mask.mapping.keymapUnsupportedType").to_string()),
+        };
+        serde_json::to_value(node)
+            .map_err(|e| format!("{}: {}", t!("mask.mapping.keymapInvalid"), e))
+    }
+}
+
+fn item_name(index: usize, mapping: &MappingType) -> String {
+    format!("{}#{}", mapping.as_ref(), index)
+}
+
+fn export_qt(config: &MappingConfig) -> Result<(Value, Vec<SkippedItem>), String> {
+    let mut exporter = QtExporter {
+        size: config.original_size.into(),
+        keymap: QtKeyMap {
+            switch_key: qt_switch_key(),
+            mouse_move_map: None,
+            key_map_nodes: Vec::new(),
+        },
+        skipped: Vec::new(),
+    };
+    for (index, mapping) in config.mappings.iter().enumerate() {
+        if let MappingType::Observation(_) = mapping {
+            continue;
+        }
+        let item = item_name(index, mapping);
+        if let Err(e) = exporter.export_mapping(&item, mapping) {
+            exporter.skipped.push(SkippedItem::new(item, e));
+        }
+    }
+    for (index, mapping) in config.mappings.iter().enumerate() {
+        if let MappingType::Observation(observation) = mapping {
+            if let Err(e) = exporter.export_observation(observation) {
+                exporter
+                    .skipped
+                    .push(SkippedItem::new(item_name(index, mapping), e));
+            }
+        }
+    }
+
+    let keymap = serde_json::to_value(&exporter.keymap)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.keymapInvalid"), e))?;
+    Ok((keymap, exporter.skipped))
+}
+
+fn export_legacy(config: &MappingConfig) -> Result<(Value, Vec<SkippedItem>), String> {
+    let mut exporter = LegacyExporter {
+        size: config.original_size.into(),
+        skipped: Vec::new(),
+    };
+    let mut keymap = LegacyKeyMap {
+        relative_size: LegacySize {
+            w: config.original_size.width,
+            h: config.original_size.height,
+        },
+        list: Vec::new(),
+    };
+    for (index, mapping) in config.mappings.iter().enumerate() {
+        let item = item_name(index, mapping);
+        match exporter.export_mapping(&item, mapping) {
+            Ok(node) => keymap.list.push(node),
+            Err(e) => exporter.skipped.push(SkippedItem::new(item, e)),
+        }
+    }
+
+    let keymap = serde_json::to_value(&keymap)
+        .map_err(|e| format!("{}: {}", t!("mask.mapping.keymapInvalid"), e))?;
+    Ok((keymap, exporter.skipped))
+}
+
+/// Convert the base mappings of a config into the keymap of another tool.
+pub fn export_keymap(
+    format: KeymapFormat,
+    config: &MappingConfig,
+) -> Result<(Value, Vec<SkippedItem>), String> {
+    let (keymap, mut skipped) = match format {
+        KeymapFormat::QtScrcpy => export_qt(config)?,
+        KeymapFormat::ScrcpyMaskLegacy => export_legacy(config)?,
+    };
+    for layer in config.layers.iter() {
+        skipped.push(SkippedItem::new(
+            format!("Layer-{}", layer.name),
+            t!("mask.mapping.keymapLayerNotSupported"),
+        ));
+    }
+    Ok((keymap, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mask::mapping::layer::{LayerMode, MappingLayer};
+
+    fn key(name: &str) -> ButtonBinding {
+        ButtonBinding::new(vec![name.parse().unwrap()])
+    }
+
+    fn absolute(x: f32, y: f32) -> Position {
+        Position {
+            x,
+            y,
+            anchor: Anchor::Absolute,
+        }
+    }
+
+    fn skipped_items(skipped: &[SkippedItem]) -> Vec<&str> {
+        skipped
+            .iter()
+            .map(|skipped| skipped.item.as_str())
+            .collect()
+    }
+
+    fn qt_keymap() -> Value {
+        json!({
+            "switchKey": "Key_QuoteLeft",
+            "mouseMoveMap": {
+                "startPos": { "x": 0.5, "y": 0.5 },
+                "speedRatioX": 2.0,
+                "speedRatioY": 4.0,
+                "smallEyes": {
+                    "type": "KMT_CLICK",
+                    "key": "Key_Alt",
+                    "pos": { "x": 0.75, "y": 0.25 },
+                    "switchMap": false
+                }
+            },
+            "keyMapNodes": [
+                {
+                    "type": "KMT_CLICK",
+                    "comment": "jump",
+                    "key": "Key_Space",
+                    "pos": { "x": 0.5, "y": 0.25 },
+                    "switchMap": false
+                },
+                {
+                    "type": "KMT_CLICK_TWICE",
+                    "key": "Key_E",
+                    "pos": { "x": 0.25, "y": 0.5 }
+                },
+                {
+                    "type": "KMT_DRAG",
+                    "key": "Key_G",
+                    "startPos": { "x": 0.1, "y": 0.1 },
+                    "endPos": { "x": 0.2, "y": 0.2 },
+                    "dragSpeed": 0.5
+                },
+                {
+                    "type": "KMT_STEER_WHEEL",
+                    "centerPos": { "x": 0.25, "y": 0.75 },
+                    "leftOffset": 0.1,
+                    "rightOffset": 0.1,
+                    "upOffset": 0.2,
+                    "downOffset": 0.2,
+                    "leftKey": "Key_A",
+                    "rightKey": "Key_D",
+                    "upKey": "Key_W",
+                    "downKey": "Key_S"
+                },
+                { "type": "KMT_GESTURE", "key": "Key_Q" },
+                { "type": "KMT_CLICK", "key": "Key_NotAKey", "pos": { "x": 0.1, "y": 0.1 } }
+            ]
+        })
+    }
+
+    fn import_qt_keymap() -> (MappingConfig, Vec<SkippedItem>) {
+        let size = Size {
+            width: 1000,
+            height: 800,
+        };
+        import_keymap(KeymapFormat::QtScrcpy, qt_keymap(), size).unwrap()
+    }
+
+    #[test]
+    fn qt_key_names_round_trip() {
+        for name in [
+            "Key_A",
+            "Key_7",
+            "Key_F12",
+            "Key_Space",
+            "Key_QuoteLeft",
+            "LeftButton",
+            "WheelUp",
+        ] {
+            assert_eq!(to_qt_key(&from_qt_key(name).unwrap()).unwrap(), name);
+        }
+        // aliases are exported by the first name of the button
+        assert_eq!(
+            to_qt_key(&from_qt_key("MiddleButton").unwrap()).unwrap(),
+            "MidButton"
+        );
+        assert_eq!(from_qt_key("Key_Alt").unwrap().to_string(), "AltLeft");
+        assert!(from_qt_key("Key_NotAKey").is_err());
+        assert!(from_qt_key("Space").is_err());
+
+        let chord = ButtonBinding::new(vec!["KeyA".parse().unwrap(), "KeyB".parse().unwrap()]);
+        assert!(to_qt_key(&chord).is_err());
+    }
+
+    #[test]
+    fn import_qt_keymap_nodes() {
+        let (config, skipped) = import_qt_keymap();
+        assert_eq!(config.original_size.width, 1000);
+        assert_eq!(config.mappings.len(), 6);
+
+        let MappingType::SingleTap(tap) = &config.mappings[0] else {
+            panic!("click should be a single tap");
+        };
+        assert_eq!(tap.position, absolute(500., 200.));
+        assert_eq!(tap.note, "jump");
+        assert_eq!(tap.bind.to_string(), "Space");
+        assert!(tap.sync);
+
+        let MappingType::MultipleTap(twice) = &config.mappings[1] else {
+            panic!("click twice should be a multiple tap");
+        };
+        assert_eq!(twice.items.len(), 2);
+        assert!(
+            twice
+                .items
+                .iter()
+                .all(|item| item.position == absolute(250., 400.))
+        );
+
+        let MappingType::Swipe(drag) = &config.mappings[2] else {
+            panic!("drag should be a swipe");
+        };
+        assert_eq!(
+            drag.positions,
+            vec![absolute(100., 80.), absolute(200., 160.)]
+        );
+        assert_eq!(drag.interval, 200);
+
+        let MappingType::DirectionPad(pad) = &config.mappings[3] else {
+            panic!("steer wheel should be a direction pad");
+        };
+        assert_eq!(pad.position, absolute(250., 600.));
+        assert_eq!((pad.max_offset_x, pad.max_offset_y), (100., 160.));
+        assert_eq!(
+            pad.bind.to_string_vec(),
+            vec!["KeyW", "KeyS", "KeyA", "KeyD"]
+        );
+
+        let MappingType::Fps(fps) = &config.mappings[4] else {
+            panic!("mouse move map should be fps");
+        };
+        assert_eq!(fps.position, absolute(500., 400.));
+        assert_eq!((fps.sensitivity_x, fps.sensitivity_y), (0.5, 0.25));
+        assert_eq!(fps.bind.to_string(), "Backquote");
+
+        let MappingType::Observation(observation) = &config.mappings[5] else {
+            panic!("small eyes should be an observation");
+        };
+        assert_eq!(observation.position, absolute(750., 200.));
+        assert_eq!(observation.bind.to_string(), "AltLeft");
+
+        // pointers are handed out in order
+        let pointer_ids: Vec<u64> = config
+            .mappings
+            .iter()
+            .flat_map(|mapping| mapping.pointer_ids())
+            .collect();
+        assert_eq!(pointer_ids, vec![1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(
+            skipped_items(&skipped),
+            vec!["keyMapNodes#4 (KMT_GESTURE)", "keyMapNodes#5 (KMT_CLICK)"]
+        );
+    }
+
+    #[test]
+    fn export_qt_keymap_round_trip() {
+        let (config, _) = import_qt_keymap();
+        let (keymap, skipped) = export_keymap(KeymapFormat::QtScrcpy, &config).unwrap();
+        assert!(skipped.is_empty());
+
+        let node_types: Vec<&str> = keymap["keyMapNodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            node_types,
+            vec![
+                "KMT_CLICK",
+                "KMT_CLICK_TWICE",
+                "KMT_DRAG",
+                "KMT_STEER_WHEEL"
+            ]
+        );
+        assert_eq!(keymap["switchKey"], "Key_QuoteLeft");
+        assert_eq!(keymap["mouseMoveMap"]["speedRatioX"], 2.0);
+        assert_eq!(keymap["mouseMoveMap"]["smallEyes"]["key"], "Key_Alt");
+
+        let (reimported, skipped) =
+            import_keymap(KeymapFormat::QtScrcpy, keymap, config.original_size).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            serde_json::to_value(&reimported.mappings).unwrap(),
+            serde_json::to_value(&config.mappings).unwrap()
+        );
+    }
+
+    #[test]
+    fn legacy_keymap_round_trip() {
+        let keymap = json!({
+            "relativeSize": { "w": 1280, "h": 720 },
+            "list": [
+                {
+                    "type": "Tap",
+                    "note": "attack",
+                    "pointerId": 1,
+                    "posX": 100.0,
+                    "posY": 200.0,
+                    "key": "M0",
+                    "time": 80
+                },
+                {
+                    "type": "SteeringWheel",
+                    "pointerId": 2,
+                    "posX": 300.0,
+                    "posY": 500.0,
+                    "key": { "left": "KeyA", "right": "KeyD", "up": "KeyW", "down": "KeyS" },
+                    "offset": 100.0
+                },
+                {
+                    "type": "Swipe",
+                    "pointerId": 3,
+                    "key": "KeyG",
+                    "pos": [{ "x": 1.0, "y": 2.0 }, { "x": 3.0, "y": 4.0 }],
+                    "intervalBetweenPos": 50
+                },
+                {
+                    "type": "Sight",
+                    "pointerId": 4,
+                    "posX": 640.0,
+                    "posY": 360.0,
+                    "key": "KeyH",
+                    "scaleX": 0.5,
+                    "scaleY": 0.5
+                },
+                { "type": "Fire", "pointerId": 5, "key": "M0" }
+            ]
+        });
+        let (config, skipped) =
+            import_keymap(KeymapFormat::ScrcpyMaskLegacy, keymap, Size::default()).unwrap();
+        assert_eq!(
+            (config.original_size.width, config.original_size.height),
+            (1280, 720)
+        );
+        assert_eq!(config.mappings.len(), 4);
+        assert_eq!(skipped_items(&skipped), vec!["list#4 (Fire)"]);
+
+        let MappingType::SingleTap(tap) = &config.mappings[0] else {
+            panic!("tap should be a single tap");
+        };
+        assert_eq!(tap.position, absolute(100., 200.));
+        assert_eq!(tap.bind.to_string(), "M-Left");
+        assert_eq!(tap.duration, 80);
+        assert_eq!(tap.pointer_id, 1);
+
+        let (exported, skipped) = export_keymap(KeymapFormat::ScrcpyMaskLegacy, &config).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(exported["relativeSize"]["w"], 1280);
+        let list = exported["list"].as_array().unwrap();
+        assert_eq!(list.len(), 4);
+        assert_eq!(list[0]["type"], "Tap");
+        assert_eq!(list[0]["key"], "M0");
+        assert_eq!(list[1]["key"]["up"], "KeyW");
+        assert_eq!(list[2]["intervalBetweenPos"], 50);
+        assert_eq!(list[3]["scaleX"], 0.5);
+    }
+
+    #[test]
+    fn skipped_items_are_reported() {
+        // offsets of both sides differ, the larger one is kept
+        let keymap = json!({
+            "keyMapNodes": [{
+                "type": "KMT_STEER_WHEEL",
+                "centerPos": { "x": 0.5, "y": 0.5 },
+                "leftOffset": 0.1,
+                "rightOffset": 0.2,
+                "upOffset": 0.1,
+                "downOffset": 0.1,
+                "leftKey": "Key_A",
+                "rightKey": "Key_D",
+                "upKey": "Key_W",
+                "downKey": "Key_S"
+            }]
+        });
+        let size = Size {
+            width: 1000,
+            height: 1000,
+        };
+        let (config, skipped) = import_keymap(KeymapFormat::QtScrcpy, keymap, size).unwrap();
+        assert_eq!(config.mappings.len(), 1);
+        assert_eq!(
+            skipped_items(&skipped),
+            vec!["keyMapNodes#0 (KMT_STEER_WHEEL)"]
+        );
+
+        let (mut config, _) = import_qt_keymap();
+        // a chord can't be exported, nor small eyes without the mouse move map, nor layers
+        let MappingType::SingleTap(tap) = &mut config.mappings[0] else {
+            panic!("click should be a single tap");
+        };
+        tap.bind = ButtonBinding::new(vec![
+            "ControlLeft".parse().unwrap(),
+            "KeyA".parse().unwrap(),
+        ]);
+        config.mappings.remove(4);
+        config.layers.push(MappingLayer {
+            name: "vehicle".to_string(),
+            mode: LayerMode::Toggle,
+            bind: key("KeyF"),
+            mappings: vec![],
+        });
+        let (keymap, skipped) = export_keymap(KeymapFormat::QtScrcpy, &config).unwrap();
+        assert_eq!(
+            skipped_items(&skipped),
+            vec!["SingleTap#0", "Observation#4", "Layer-vehicle"]
+        );
+        assert_eq!(keymap["keyMapNodes"].as_array().unwrap().len(), 3);
+
+        // the legacy format has no multiple tap, and a single value for both axes
+        let (_, skipped) = export_keymap(KeymapFormat::ScrcpyMaskLegacy, &config).unwrap();
+        assert_eq!(
+            skipped_items(&skipped),
+            vec![
+                "SingleTap#0",
+                "MultipleTap#1",
+                "DirectionPad#3",
+                "Observation#4",
+                "Layer-vehicle"
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use bevy::{
+    ecs::system::{Res, ResMut, SystemParam},
+    state::state::{NextState, State},
+};
+use bevy_ineffable::prelude::*;
+use rust_i18n::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mask::{
+        mapping::{
+            binding::{ButtonBinding, ValidateMappingConfig},
+            cast_spell::ActiveCastSpell,
+            config::{ActiveMappingConfig, BindMappingConfig, MappingAction, MappingType},
+            cursor::{ActiveCursorFpsConfig, CursorPosition, CursorState},
+            direction_pad::{BlockDirectionPad, DirectionPadMap},
+            fire::ActiveFireMap,
+            observation::ActiveObservation,
+            tap::{ActiveRepeatTapMap, ActiveSingleTapMap},
+            utils::ControlMsgHelper,
+        },
+        mask_command::MaskSize,
+    },
+    scrcpy::constant::MotionEventAction,
+    utils::ChannelSenderCS,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+pub enum LayerMode {
+    /// active while the key is held
+    #[default]
+    Hold,
+    /// switched on and off by pressing the key
+    Toggle,
+}
+
+/// A named group of mappings which is only active when its key is held or toggled on.
+/// Mappings outside of layers are always active.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct MappingLayer {
+    pub name: String,
+    #[serde(default)]
+    pub mode: LayerMode,
+    pub bind: ButtonBinding,
+    pub mappings: Vec<MappingType>,
+}
+
+impl ValidateMappingConfig for MappingLayer {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err(t!("mask.mapping.layerNameEmpty").to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BindMappingLayer {
+    pub name: String,
+    pub mode: LayerMode,
+    pub bind: ButtonBinding,
+    pub action: MappingAction,
+    pub input_binding: InputBinding,
+}
+
+impl BindMappingLayer {
+    pub fn new(layer: &MappingLayer, action: MappingAction) -> Self {
+        Self {
+            name: layer.name.clone(),
+            mode: layer.mode,
+            bind: layer.bind.clone(),
+            action,
+            input_binding: ContinuousBinding::hold(layer.bind.clone()).0,
+        }
+    }
+}
+
+/// Touches held by mappings, which handlers won't lift once their mappings are gone.
+#[derive(SystemParam)]
+pub struct HeldTouches<'w> {
+    cs_tx_res: Res<'w, ChannelSenderCS>,
+    mask_size: Res<'w, MaskSize>,
+    cursor_pos: ResMut<'w, CursorPosition>,
+    fps_config: ResMut<'w, ActiveCursorFpsConfig>,
+    cursor_state: Res<'w, State<CursorState>>,
+    next_cursor_state: ResMut<'w, NextState<CursorState>>,
+    single_tap_map: ResMut<'w, ActiveSingleTapMap>,
+    repeat_tap_map: ResMut<'w, ActiveRepeatTapMap>,
+    direction_pad_map: ResMut<'w, DirectionPadMap>,
+    block_direction_pad: ResMut<'w, BlockDirectionPad>,
+    observation_map: ResMut<'w, ActiveObservation>,
+    active_cast: ResMut<'w, ActiveCastSpell>,
+    fire_map: ResMut<'w, ActiveFireMap>,
+}
+
+impl HeldTouches<'_> {
+    /// Lift the touches of the mapping with the action `key`.
+    pub fn release(&mut self, key: &str) {
+        let mask_size = self.mask_size.0;
+        self.single_tap_map.release(key, &self.cs_tx_res);
+        self.repeat_tap_map.stop(key);
+        self.direction_pad_map.release(key, &self.cs_tx_res);
+        self.observation_map
+            .release(key, &self.cs_tx_res, mask_size, self.cursor_pos.0);
+        self.active_cast.release(
+            key,
+            &self.cs_tx_res,
+            mask_size,
+            &mut self.block_direction_pad,
+        );
+        self.fire_map.release(
+            key,
+            &self.cs_tx_res,
+            mask_size,
+            &mut self.fps_config,
+            &mut self.cursor_pos,
+        );
+    }
+
+    /// Lift the touches of all mappings of the config and leave fps mode.
+    pub fn release_all(&mut self, config: &BindMappingConfig) {
+        for action in config.all_mappings.keys() {
+            self.release(&action.to_string());
+        }
+        if *self.cursor_state.get() == CursorState::Fps {
+            ControlMsgHelper::send_touch(
+                &self.cs_tx_res.0,
+                MotionEventAction::Up,
+                self.fps_config.pointer_id,
+                self.mask_size.0, // cursor_pos is related to mask size
+                self.cursor_pos.0,
+            );
+            self.next_cursor_state.set(CursorState::Normal);
+        }
+    }
+}
+
+pub fn handle_layers(
+    ineffable: Res<Ineffable>,
+    mut active_mapping: ResMut<ActiveMappingConfig>,
+    mut held_touches: HeldTouches,
+) {
+    let mut changes: Vec<(String, bool)> = Vec::new();
+    if let Some(config) = &active_mapping.0 {
+        for layer in &config.layers {
+            let action = layer.action.ineff_continuous();
+            let active = config.active_layers.contains(&layer.name);
+            match layer.mode {
+                LayerMode::Hold => {
+                    if ineffable.is_active(action) != active {
+                        changes.push((layer.name.clone(), !active));
+                    }
+                }
+                LayerMode::Toggle => {
+                    if ineffable.just_activated(action) {
+                        changes.push((layer.name.clone(), !active));
+                    }
+                }
+            }
+        }
+    }
+
+    // only mutate when needed, as labels are redrawn on change
+    if !changes.is_empty() {
+        let config = active_mapping.0.as_mut().unwrap();
+        for (name, active) in changes {
+            log::info!(
+                "[Mapping] {}",
+                t!("mask.mapping.layerActiveChanged", name => name, active => active)
+            );
+            if !active {
+                // handlers won't see the mappings of this layer anymore, so lift their touches here
+                let keys: Vec<String> = config
+                    .mapping_layer
+                    .iter()
+                    .filter(|(_, layer)| **layer == name)
+                    .map(|(action, _)| action.to_string())
+                    .collect();
+                for key in keys {
+                    held_touches.release(&key);
+                }
+            }
+            config.set_layer_active(&name, active);
+        }
+    }
+}
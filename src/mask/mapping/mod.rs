@@ -1,16 +1,25 @@
 pub mod binding;
+pub mod bundle;
 pub mod cast_spell;
+pub mod check;
 pub mod config;
 pub mod cursor;
 pub mod direction_pad;
 pub mod fire;
+pub mod gesture;
+pub mod history;
+pub mod keymap;
+pub mod layer;
 pub mod observation;
 pub mod raw_input;
 pub mod script;
 pub mod script_helper;
+pub mod scroll;
 pub mod swipe;
+pub mod switch_mapping;
 pub mod tap;
 pub mod utils;
+pub mod version;
 
 use bevy::prelude::*;
 use bevy_ineffable::prelude::*;
@@ -20,12 +29,13 @@ use crate::{
     config::LocalConfig,
     mask::mapping::{
         config::{
-            ActiveMappingConfig, BindMappingConfig, MappingAction, default_mapping_config,
+            ActionSlot, ActiveMappingConfig, apply_mapping_input, default_mapping_config,
             load_mapping_config, save_mapping_config,
         },
         cursor::{CursorPlugins, CursorState},
+        utils::{MappingSettings, Size},
     },
-    utils::{relate_to_data_path},
+    utils::relate_to_data_path,
 };
 
 #[derive(States, Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
@@ -43,7 +53,7 @@ impl Plugin for MappingPlugins {
         app.add_plugins((IneffablePlugin, CursorPlugins))
             .insert_state(MappingState::Stop)
             .insert_resource(ActiveMappingConfig(None, String::new()))
-            .register_input_action::<MappingAction>()
+            .register_input_action::<ActionSlot>()
             .add_systems(
                 Startup,
                 (
@@ -58,6 +68,12 @@ impl Plugin for MappingPlugins {
                 ),
             )
             // normal mapping mode
+            .add_systems(
+                Update,
+                layer::handle_layers
+                    .before(tap::handle_single_tap)
+                    .run_if(in_state(MappingState::Normal)),
+            )
             .add_systems(
                 Update,
                 (
@@ -66,6 +82,8 @@ impl Plugin for MappingPlugins {
                     tap::handle_repeat_tap_trigger,
                     tap::handle_multiple_tap,
                     swipe::handle_swipe,
+                    gesture::handle_gesture,
+                    scroll::handle_scroll,
                     direction_pad::handle_direction_pad,
                     cast_spell::handle_mouse_cast_spell,
                     cast_spell::handle_mouse_cast_spell_trigger,
@@ -75,6 +93,7 @@ impl Plugin for MappingPlugins {
                     observation::handle_observation,
                     observation::handle_observation_trigger,
                     fire::handle_fps,
+                    fire::handle_fps_aim.run_if(in_state(CursorState::Fps)),
                     // raw input won't work in fps mode
                     raw_input::handle_raw_input.run_if(not(in_state(CursorState::Fps))),
                     // fire only works in fps mode
@@ -82,6 +101,7 @@ impl Plugin for MappingPlugins {
                         .run_if(in_state(CursorState::Fps)),
                     script::handle_script,
                     script::handle_script_trigger,
+                    switch_mapping::handle_switch_mapping,
                 )
                     .run_if(in_state(MappingState::Normal)),
             )
@@ -105,18 +125,19 @@ impl Plugin for MappingPlugins {
     }
 }
 
-fn init(mut ineffable: IneffableCommands, mut active_mapping: ResMut<ActiveMappingConfig>) {
+fn init(mut commands: Commands, mut active_mapping: ResMut<ActiveMappingConfig>) {
     let config = LocalConfig::get();
+    commands.insert_resource(MappingSettings::from_local_config());
 
-    let (bind_mapping_config, input_config, file) =
-        match load_mapping_config(&config.active_mapping_file) {
-            Ok((mapping_config, input_config)) => {
+    let (bind_mapping_config, file) =
+        match load_mapping_config(&config.active_mapping_file, Size::default()) {
+            Ok(mapping_config) => {
                 log::info!(
                     "[Mask] {}: {}",
                     t!("mask.mapping.usingMappingConfig"),
                     config.active_mapping_file,
                 );
-                (mapping_config, input_config, config.active_mapping_file)
+                (mapping_config, config.active_mapping_file)
             }
             Err(e) => {
                 log::error!("{}", e);
@@ -128,16 +149,10 @@ fn init(mut ineffable: IneffableCommands, mut active_mapping: ResMut<ActiveMappi
                 let config_path = relate_to_data_path(["mapping", "default.json"]);
                 save_mapping_config(&default_mapping, &config_path).unwrap();
                 LocalConfig::set_active_mapping_file("default.json".to_string());
-                let default_bind_mapping: BindMappingConfig = default_mapping.into();
-                let input_config: InputConfig = InputConfig::from(&default_bind_mapping);
-                (
-                    default_bind_mapping,
-                    input_config,
-                    "default.json".to_string(),
-                )
+                (default_mapping.into(), "default.json".to_string())
             }
         };
     active_mapping.0 = Some(bind_mapping_config);
     active_mapping.1 = file;
-    ineffable.set_config(&input_config);
+    commands.queue(apply_mapping_input);
 }
@@ -8,6 +8,7 @@ use bevy::{
     math::Vec2,
 };
 use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -53,7 +54,7 @@ impl From<MappingObservation> for BindMappingObservation {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingObservation {
     pub note: String,
     pub pointer_id: u64,
@@ -75,6 +76,28 @@ struct ObservationItem {
     sensitivity: Vec2,
 }
 
+impl ActiveObservation {
+    pub fn release(
+        &mut self,
+        key: &str,
+        cs_tx: &ChannelSenderCS,
+        mask_size: Vec2,
+        cursor_pos: Vec2,
+    ) {
+        if let Some(item) = self.0.remove(key) {
+            // touch up
+            let delta = (cursor_pos - item.start_cursor_pos) * item.sensitivity;
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Up,
+                item.pointer_id,
+                mask_size,
+                item.mask_pos + delta,
+            );
+        }
+    }
+}
+
 pub fn handle_observation_trigger(
     cs_tx_res: Res<ChannelSenderCS>,
     mask_size: Res<MaskSize>,
@@ -102,46 +125,33 @@ pub fn handle_observation(
     mut active_map: ResMut<ActiveObservation>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("Observation") {
-                let mapping = mapping.as_ref_observation();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let original_pos: Vec2 = mapping.position.into();
-                    let sensitivity: Vec2 = (mapping.sensitivity_x, mapping.sensitivity_y).into();
-                    let pointer_id = mapping.pointer_id;
-                    let mask_pos = original_pos / original_size * mask_size.0;
-                    // touch down
-                    ControlMsgHelper::send_touch(
-                        &cs_tx_res.0,
-                        MotionEventAction::Down,
-                        pointer_id,
-                        mask_size.0,
+        for (action, mapping) in &active_mapping.index.observation {
+            if ineffable.just_activated(action.ineff_continuous()) {
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let original_pos: Vec2 = mapping.position.into();
+                let sensitivity: Vec2 = (mapping.sensitivity_x, mapping.sensitivity_y).into();
+                let pointer_id = mapping.pointer_id;
+                let mask_pos = original_pos / original_size * mask_size.0;
+                // touch down
+                ControlMsgHelper::send_touch(
+                    &cs_tx_res.0,
+                    MotionEventAction::Down,
+                    pointer_id,
+                    mask_size.0,
+                    mask_pos,
+                );
+                // add to active_map
+                active_map.0.insert(
+                    action.to_string(),
+                    ObservationItem {
+                        start_cursor_pos: cursor_pos.0,
                         mask_pos,
-                    );
-                    // add to active_map
-                    active_map.0.insert(
-                        action.to_string(),
-                        ObservationItem {
-                            start_cursor_pos: cursor_pos.0,
-                            mask_pos,
-                            pointer_id,
-                            sensitivity,
-                        },
-                    );
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    if let Some(item) = active_map.0.remove(action.as_ref()) {
-                        // touch up
-                        let delta = (cursor_pos.0 - item.start_cursor_pos) * item.sensitivity;
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Up,
-                            item.pointer_id,
-                            mask_size.0,
-                            item.mask_pos + delta,
-                        );
-                    }
-                }
+                        pointer_id,
+                        sensitivity,
+                    },
+                );
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                active_map.release(action.as_ref(), &cs_tx_res, mask_size.0, cursor_pos.0);
             }
         }
     }
@@ -17,6 +17,7 @@ use bevy::{
 use bevy_ineffable::prelude::{Ineffable, InputBinding, PulseBinding};
 use copypasta::{ClipboardContext, ClipboardProvider};
 use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -54,7 +55,7 @@ impl From<MappingRawInput> for BindMappingRawInput {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingRawInput {
     pub note: String,
     pub position: Position,
@@ -69,13 +70,11 @@ pub fn handle_raw_input(
     mut next_state: ResMut<NextState<MappingState>>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, _) in &active_mapping.mappings {
-            if action.as_ref().starts_with("RawInput") {
-                if ineffable.just_pulsed(action.ineff_pulse()) {
-                    next_state.set(MappingState::RawInput);
-                    log::info!("[Mapping] {}", t!("mask.mapping.rawInputModeHint"));
-                    return;
-                }
+        for (action, _) in &active_mapping.index.raw_input {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                next_state.set(MappingState::RawInput);
+                log::info!("[Mapping] {}", t!("mask.mapping.rawInputModeHint"));
+                return;
             }
         }
     }
@@ -11,6 +11,7 @@ use bevy::{
 use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding};
 use bevy_tokio_tasks::TokioTasksRuntime;
 use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -61,7 +62,7 @@ impl From<MappingScript> for BindMappingScript {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingScript {
     pub position: Position,
     pub note: String,
@@ -106,57 +107,46 @@ pub fn handle_script(
     mut active_map: ResMut<ActiveScriptMap>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("Script") {
-                let mapping = mapping.as_ref_script();
-                let original_size: Vec2 = active_mapping.original_size.into();
-                let cs_tx = cs_tx_res.0.clone();
-                let cursor_pos = cursor_pos_res.0.clone();
-                let interval = Duration::from_millis(mapping.interval as u64);
-
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    if !mapping.pressed_script_ast.empty {
-                        let ast = mapping.pressed_script_ast.clone();
-                        runtime.spawn_background_task(move |_ctx| async move {
-                            if let Err(e) = ast.eval_script(&cs_tx, original_size, cursor_pos) {
-                                log::error!(
-                                    "{}: {}",
-                                    t!("mask.mapping.pressedScriptRuntimeError"),
-                                    e
-                                );
-                            }
-                        });
-                    }
-
-                    if !mapping.held_script_ast.empty {
-                        let mut timer = Timer::new(interval, TimerMode::Repeating);
-                        timer.tick(interval);
-                        active_map.0.insert(
-                            action.to_string(),
-                            ScriptTimer {
-                                timer,
-                                original_size: original_size,
-                                held_script_ast: mapping.held_script_ast.clone(),
-                            },
-                        );
-                    }
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    if !mapping.held_script_ast.empty {
-                        active_map.0.remove(action.as_ref());
-                    }
-
-                    if !mapping.released_script_ast.empty {
-                        let ast = mapping.released_script_ast.clone();
-                        runtime.spawn_background_task(move |_ctx| async move {
-                            if let Err(e) = ast.eval_script(&cs_tx, original_size, cursor_pos) {
-                                log::error!(
-                                    "{}: {}",
-                                    t!("mask.mapping.releasedScriptRuntimeError"),
-                                    e
-                                );
-                            }
-                        });
-                    }
+        for (action, mapping) in &active_mapping.index.script {
+            let original_size: Vec2 = active_mapping.original_size.into();
+            let cs_tx = cs_tx_res.0.clone();
+            let cursor_pos = cursor_pos_res.0.clone();
+            let interval = Duration::from_millis(mapping.interval as u64);
+
+            if ineffable.just_activated(action.ineff_continuous()) {
+                if !mapping.pressed_script_ast.empty {
+                    let ast = mapping.pressed_script_ast.clone();
+                    runtime.spawn_background_task(move |_ctx| async move {
+                        if let Err(e) = ast.eval_script(&cs_tx, original_size, cursor_pos) {
+                            log::error!("{}: {}", t!("mask.mapping.pressedScriptRuntimeError"), e);
+                        }
+                    });
+                }
+
+                if !mapping.held_script_ast.empty {
+                    let mut timer = Timer::new(interval, TimerMode::Repeating);
+                    timer.tick(interval);
+                    active_map.0.insert(
+                        action.to_string(),
+                        ScriptTimer {
+                            timer,
+                            original_size: original_size,
+                            held_script_ast: mapping.held_script_ast.clone(),
+                        },
+                    );
+                }
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                if !mapping.held_script_ast.empty {
+                    active_map.0.remove(action.as_ref());
+                }
+
+                if !mapping.released_script_ast.empty {
+                    let ast = mapping.released_script_ast.clone();
+                    runtime.spawn_background_task(move |_ctx| async move {
+                        if let Err(e) = ast.eval_script(&cs_tx, original_size, cursor_pos) {
+                            log::error!("{}: {}", t!("mask.mapping.releasedScriptRuntimeError"), e);
+                        }
+                    });
                 }
             }
         }
@@ -0,0 +1,149 @@
+use bevy::{
+    ecs::system::Res,
+    input::{
+        ButtonInput,
+        keyboard::KeyCode,
+        mouse::{AccumulatedMouseScroll, MouseScrollUnit},
+    },
+    math::Vec2,
+    state::state::State,
+};
+use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding};
+use rust_i18n::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mask::{
+        mapping::{
+            binding::{ButtonBinding, ValidateMappingConfig},
+            config::ActiveMappingConfig,
+            cursor::{CursorPosition, CursorState},
+            utils::{ControlMsgHelper, MappingSettings, Position},
+        },
+        mask_command::MaskSize,
+    },
+    utils::ChannelSenderCS,
+};
+
+// touchpads report pixels instead of lines
+const SCROLL_PIXELS_PER_STEP: f32 = 100.;
+
+#[derive(Debug, Clone)]
+pub struct BindMappingScroll {
+    pub note: String,
+    pub position: Position,
+    pub scale: f32,
+    pub shift_horizontal: bool,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<MappingScroll> for BindMappingScroll {
+    fn from(value: MappingScroll) -> Self {
+        Self {
+            note: value.note,
+            position: value.position,
+            scale: value.scale,
+            shift_horizontal: value.shift_horizontal,
+            bind: value.bind.clone(),
+            input_binding: ContinuousBinding::hold(value.bind).0,
+        }
+    }
+}
+
+/// Forward the mouse wheel as scroll events at `position` while the key is held.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct MappingScroll {
+    pub note: String,
+    pub position: Position,
+    // scroll steps sent for each wheel notch
+    pub scale: f32,
+    // scroll horizontally while shift is held
+    #[serde(default)]
+    pub shift_horizontal: bool,
+    pub bind: ButtonBinding,
+}
+
+impl ValidateMappingConfig for MappingScroll {
+    fn validate(&self) -> Result<(), String> {
+        if self.scale <= 0. {
+            return Err(t!("mask.mapping.scrollInvalidScale").to_string());
+        }
+        Ok(())
+    }
+}
+
+// scroll steps of the wheel, positive to scroll right and up
+fn scroll_delta(
+    scroll: &AccumulatedMouseScroll,
+    shift_pressed: bool,
+    scale: f32,
+    shift_horizontal: bool,
+) -> Vec2 {
+    let mut delta = scroll.delta;
+    if scroll.unit == MouseScrollUnit::Pixel {
+        delta /= SCROLL_PIXELS_PER_STEP;
+    }
+    if shift_horizontal && shift_pressed && delta.x == 0. {
+        // wheel down scrolls to the right, like most desktop apps
+        delta = Vec2::new(-delta.y, 0.);
+    }
+    delta * scale
+}
+
+pub fn handle_scroll(
+    ineffable: Res<Ineffable>,
+    active_mapping: Res<ActiveMappingConfig>,
+    accumulated_scroll: Res<AccumulatedMouseScroll>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    cursor_state: Res<State<CursorState>>,
+    cursor_pos: Res<CursorPosition>,
+    mask_size: Res<MaskSize>,
+    cs_tx_res: Res<ChannelSenderCS>,
+    settings: Res<MappingSettings>,
+) {
+    if accumulated_scroll.delta == Vec2::ZERO {
+        return;
+    }
+    let shift_pressed = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if let Some(active_mapping) = &active_mapping.0 {
+        for (action, mapping) in &active_mapping.index.scroll {
+            if ineffable.is_active(action.ineff_continuous()) {
+                let delta = scroll_delta(
+                    &accumulated_scroll,
+                    shift_pressed,
+                    mapping.scale,
+                    mapping.shift_horizontal,
+                );
+                ControlMsgHelper::send_scroll(
+                    &cs_tx_res.0,
+                    active_mapping.original_size.into(),
+                    mapping.position.into(),
+                    delta,
+                );
+                return;
+            }
+        }
+        // wheel is used to trigger other mappings
+        if active_mapping.is_wheel_bound() {
+            return;
+        }
+    }
+
+    // there is no cursor on the device screen in fps mode
+    if *cursor_state.get() == CursorState::Fps {
+        return;
+    }
+    let forwarding = &settings.scroll_forwarding;
+    if forwarding.enable {
+        let delta = scroll_delta(
+            &accumulated_scroll,
+            shift_pressed,
+            forwarding.scale,
+            forwarding.shift_horizontal,
+        );
+        ControlMsgHelper::send_scroll(&cs_tx_res.0, mask_size.0, cursor_pos.0, delta);
+    }
+}
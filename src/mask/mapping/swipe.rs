@@ -6,6 +6,8 @@ use bevy::{
 };
 use bevy_ineffable::prelude::*;
 use bevy_tokio_tasks::TokioTasksRuntime;
+use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
@@ -13,7 +15,10 @@ use crate::{
     mask::mapping::{
         binding::{ButtonBinding, ValidateMappingConfig},
         config::ActiveMappingConfig,
-        utils::{ControlMsgHelper, MIN_MOVE_STEP_INTERVAL, Position, ease_sigmoid_like},
+        utils::{
+            ControlMsgHelper, Easing, Humanize, MIN_MOVE_STEP_INTERVAL, MappingSettings, Position,
+            bezier_point, path_offset,
+        },
     },
     scrcpy::constant::MotionEventAction,
     utils::ChannelSenderCS,
@@ -25,6 +30,10 @@ pub struct BindMappingSwipe {
     pub pointer_id: u64,
     pub positions: Vec<Position>,
     pub interval: u64,
+    pub easing: Easing,
+    pub segments: Vec<SwipeSegment>,
+    pub hold: u64,
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
     pub input_binding: InputBinding,
 }
@@ -36,18 +45,46 @@ impl From<MappingSwipe> for BindMappingSwipe {
             pointer_id: value.pointer_id,
             positions: value.positions,
             interval: value.interval,
+            easing: value.easing,
+            segments: value.segments,
+            hold: value.hold,
+            humanize: value.humanize,
             bind: value.bind.clone(),
             input_binding: PulseBinding::just_pressed(value.bind).0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Options of the move from `positions[i]` to `positions[i + 1]`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct SwipeSegment {
+    /// duration of the move in ms, `interval` of the swipe if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// `easing` of the swipe if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub easing: Option<Easing>,
+    /// control points of a Bezier curve to the next position, a straight line if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_points: Vec<Position>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingSwipe {
     pub note: String,
     pub pointer_id: u64,
     pub positions: Vec<Position>,
     pub interval: u64,
+    #[serde(default)]
+    pub easing: Easing,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<SwipeSegment>,
+    /// time in ms to stay at the last position before lifting
+    #[serde(default)]
+    pub hold: u64,
+    /// overrides the global humanization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
 }
 
@@ -56,7 +93,24 @@ impl ValidateMappingConfig for MappingSwipe {
         if self.positions.is_empty() {
             return Err("Swipe's position list is empty".to_string());
         }
-        Ok(())
+        if self.segments.len() >= self.positions.len() {
+            return Err(t!(
+                "mask.mapping.swipeTooManySegments",
+                segments => self.segments.len(),
+                positions => self.positions.len()
+            )
+            .to_string());
+        }
+        self.easing.validate()?;
+        for segment in self.segments.iter() {
+            if let Some(easing) = &segment.easing {
+                easing.validate()?;
+            }
+        }
+        match &self.humanize {
+            Some(humanize) => humanize.validate(),
+            None => Ok(()),
+        }
     }
 }
 
@@ -65,58 +119,78 @@ pub fn handle_swipe(
     active_mapping: Res<ActiveMappingConfig>,
     cs_tx_res: Res<ChannelSenderCS>,
     runtime: ResMut<TokioTasksRuntime>,
+    settings: Res<MappingSettings>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("Swipe") {
-                let mapping = mapping.as_ref_swipe();
-                let original_size: Vec2 = active_mapping.original_size.into();
-                if ineffable.just_pulsed(action.ineff_pulse()) {
-                    let cs_tx = cs_tx_res.0.clone();
-                    let pointer_id = mapping.pointer_id;
-                    let points = mapping.positions.clone();
-                    let interval = mapping.interval;
-                    runtime.spawn_background_task(move |_ctx| async move {
-                        ControlMsgHelper::send_touch(
-                            &cs_tx,
-                            MotionEventAction::Down,
-                            pointer_id,
-                            original_size,
-                            points[0].into(),
-                        );
-                        let mut cur_pos: Vec2 = points[0].into();
-                        for i in 1..points.len() {
-                            let next_pos: Vec2 = points[i].into();
+        for (action, mapping) in &active_mapping.index.swipe {
+            let original_size: Vec2 = active_mapping.original_size.into();
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                let cs_tx = cs_tx_res.0.clone();
+                let pointer_id = mapping.pointer_id;
+                let mut humanizer = settings.humanizer(action.as_ref(), mapping.humanize.as_ref());
+                let points: Vec<Vec2> = mapping
+                    .positions
+                    .iter()
+                    .map(|&p| humanizer.position(p.into()))
+                    .collect();
+                let bend = humanizer.path_bend();
+                let segments = mapping.segments.clone();
+                let interval = mapping.interval;
+                let easing = mapping.easing;
+                let hold = mapping.hold;
+                runtime.spawn_background_task(move |_ctx| async move {
+                    ControlMsgHelper::send_touch(
+                        &cs_tx,
+                        MotionEventAction::Down,
+                        pointer_id,
+                        original_size,
+                        points[0],
+                    );
+                    let mut cur_pos = points[0];
+                    for i in 1..points.len() {
+                        let next_pos = points[i];
 
-                            let delta = next_pos - cur_pos;
-                            let steps = std::cmp::max(1, interval / MIN_MOVE_STEP_INTERVAL);
-                            let step_duration = interval / steps;
+                        let segment = segments.get(i - 1);
+                        let duration = humanizer
+                            .duration(segment.and_then(|s| s.duration).unwrap_or(interval));
+                        let easing = segment.and_then(|s| s.easing).unwrap_or(easing);
+                        let mut path: Vec<Vec2> = vec![cur_pos];
+                        if let Some(segment) = segment {
+                            path.extend(segment.control_points.iter().map(|&p| Vec2::from(p)));
+                        }
+                        path.push(next_pos);
 
-                            for step in 1..=steps {
-                                let linear_t = step as f32 / steps as f32;
-                                let eased_t = ease_sigmoid_like(linear_t);
-                                let interp = cur_pos + delta * eased_t;
-                                ControlMsgHelper::send_touch(
-                                    &cs_tx,
-                                    MotionEventAction::Move,
-                                    pointer_id,
-                                    original_size,
-                                    interp.into(),
-                                );
-                                sleep(Duration::from_millis(step_duration as u64)).await;
-                            }
+                        let steps = std::cmp::max(1, duration / MIN_MOVE_STEP_INTERVAL);
+                        let step_duration = duration / steps;
 
-                            cur_pos = next_pos;
+                        for step in 1..=steps {
+                            let linear_t = step as f32 / steps as f32;
+                            let eased_t = easing.apply(linear_t);
+                            let interp = bezier_point(&path, eased_t)
+                                + path_offset(bend, cur_pos, next_pos, linear_t);
+                            ControlMsgHelper::send_touch(
+                                &cs_tx,
+                                MotionEventAction::Move,
+                                pointer_id,
+                                original_size,
+                                interp,
+                            );
+                            sleep(Duration::from_millis(step_duration as u64)).await;
                         }
-                        ControlMsgHelper::send_touch(
-                            &cs_tx,
-                            MotionEventAction::Up,
-                            pointer_id,
-                            original_size,
-                            cur_pos.into(),
-                        );
-                    });
-                }
+
+                        cur_pos = next_pos;
+                    }
+                    if hold > 0 {
+                        sleep(Duration::from_millis(hold)).await;
+                    }
+                    ControlMsgHelper::send_touch(
+                        &cs_tx,
+                        MotionEventAction::Up,
+                        pointer_id,
+                        original_size,
+                        cur_pos,
+                    );
+                });
             }
         }
     }
@@ -0,0 +1,112 @@
+use bevy::ecs::system::{Commands, Res, ResMut};
+use bevy_ineffable::prelude::*;
+use rust_i18n::t;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mask::{
+        mapping::{
+            binding::{ButtonBinding, ValidateMappingConfig},
+            config::{ActiveMappingConfig, apply_mapping_input, load_mapping_config},
+            layer::HeldTouches,
+            utils::Position,
+        },
+        mask_command::DeviceSize,
+    },
+    utils::is_safe_file_name,
+};
+
+#[derive(Debug, Clone)]
+pub struct BindMappingSwitchMapping {
+    pub note: String,
+    pub position: Position,
+    pub files: Vec<String>,
+    pub bind: ButtonBinding,
+    pub input_binding: InputBinding,
+}
+
+impl From<MappingSwitchMapping> for BindMappingSwitchMapping {
+    fn from(value: MappingSwitchMapping) -> Self {
+        Self {
+            note: value.note,
+            position: value.position,
+            files: value.files,
+            bind: value.bind.clone(),
+            input_binding: PulseBinding::just_pressed(value.bind).0,
+        }
+    }
+}
+
+/// Jump to the mapping file if only one is given, otherwise cycle through the list.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct MappingSwitchMapping {
+    pub note: String,
+    pub position: Position,
+    pub files: Vec<String>,
+    pub bind: ButtonBinding,
+}
+
+impl ValidateMappingConfig for MappingSwitchMapping {
+    fn validate(&self) -> Result<(), String> {
+        if self.files.is_empty() {
+            return Err("SwitchMapping's file list is empty".to_string());
+        }
+        for file in &self.files {
+            if !is_safe_file_name(file) {
+                return Err(format!("{}: {}", t!("mask.mapping.fileNameNotSafe"), file));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BindMappingSwitchMapping {
+    fn next_file(&self, current: &str) -> &String {
+        match self.files.iter().position(|file| file == current) {
+            Some(index) => &self.files[(index + 1) % self.files.len()],
+            None => &self.files[0],
+        }
+    }
+}
+
+pub fn handle_switch_mapping(
+    ineffable: Res<Ineffable>,
+    mut commands: Commands,
+    mut active_mapping: ResMut<ActiveMappingConfig>,
+    device_size: Res<DeviceSize>,
+    mut held_touches: HeldTouches,
+) {
+    let mut target: Option<String> = None;
+    if let Some(mapping_config) = &active_mapping.0 {
+        for (action, mapping) in &mapping_config.index.switch_mapping {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                target = Some(mapping.next_file(&active_mapping.1).clone());
+                break;
+            }
+        }
+    }
+
+    if let Some(file_name) = target {
+        if file_name == active_mapping.1 {
+            return;
+        }
+        match load_mapping_config(&file_name, device_size.0) {
+            Ok(mapping_config) => {
+                // handlers won't see the old mappings anymore, so lift their touches here
+                if let Some(old_config) = &active_mapping.0 {
+                    held_touches.release_all(old_config);
+                }
+                active_mapping.0 = Some(mapping_config);
+                commands.queue(apply_mapping_input);
+                active_mapping.1 = file_name.clone();
+                log::info!(
+                    "[Mapping] {}: {}",
+                    t!("mask.mapping.switchMappingSuccess"),
+                    file_name
+                );
+            }
+            Err(e) => log::error!("[Mapping] {}", e),
+        }
+    }
+}
@@ -10,6 +10,7 @@ use bevy::{
 };
 use bevy_ineffable::prelude::{ContinuousBinding, Ineffable, InputBinding, PulseBinding};
 use bevy_tokio_tasks::TokioTasksRuntime;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::time::sleep;
 
@@ -18,7 +19,7 @@ use crate::{
         mapping::{
             binding::{ButtonBinding, ValidateMappingConfig},
             config::ActiveMappingConfig,
-            utils::{ControlMsgHelper, Position},
+            utils::{ControlMsgHelper, Humanize, Humanizer, MappingSettings, Position},
         },
         mask_command::MaskSize,
     },
@@ -27,6 +28,7 @@ use crate::{
 };
 
 pub fn tap_init(mut commands: Commands) {
+    commands.insert_resource(ActiveSingleTapMap::default());
     commands.insert_resource(ActiveRepeatTapMap::default());
 }
 
@@ -37,6 +39,7 @@ pub struct BindMappingSingleTap {
     pub pointer_id: u64,
     pub duration: u64,
     pub sync: bool,
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
     pub input_binding: InputBinding,
 }
@@ -49,80 +52,118 @@ impl From<MappingSingleTap> for BindMappingSingleTap {
             pointer_id: value.pointer_id,
             duration: value.duration,
             sync: value.sync,
+            humanize: value.humanize,
             bind: value.bind.clone(),
             input_binding: ContinuousBinding::hold(value.bind).0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingSingleTap {
     pub position: Position,
     pub note: String,
     pub pointer_id: u64,
     pub duration: u64,
     pub sync: bool,
+    /// overrides the global humanization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
 }
 
-impl ValidateMappingConfig for MappingSingleTap {}
+impl ValidateMappingConfig for MappingSingleTap {
+    fn validate(&self) -> Result<(), String> {
+        match &self.humanize {
+            Some(humanize) => humanize.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+// sync taps being held, so that they are lifted where they were pressed
+#[derive(Resource, Default)]
+pub struct ActiveSingleTapMap(pub HashMap<String, SingleTapItem>);
+
+pub struct SingleTapItem {
+    pub pointer_id: u64,
+    pub original_size: Vec2,
+    pub original_pos: Vec2,
+}
+
+impl ActiveSingleTapMap {
+    pub fn release(&mut self, key: &str, cs_tx: &ChannelSenderCS) {
+        if let Some(item) = self.0.remove(key) {
+            // Tap up sync
+            ControlMsgHelper::send_touch(
+                &cs_tx.0,
+                MotionEventAction::Up,
+                item.pointer_id,
+                item.original_size,
+                item.original_pos,
+            );
+        }
+    }
+}
 
 pub fn handle_single_tap(
     ineffable: Res<Ineffable>,
     active_mapping: Res<ActiveMappingConfig>,
     cs_tx_res: Res<ChannelSenderCS>,
     runtime: ResMut<TokioTasksRuntime>,
+    settings: Res<MappingSettings>,
+    mut active_map: ResMut<ActiveSingleTapMap>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("SingleTap") {
-                let original_size: Vec2 = active_mapping.original_size.into();
-                let mapping = mapping.as_ref_singletap();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    if mapping.sync {
-                        // Tap down sync
-                        ControlMsgHelper::send_touch(
-                            &cs_tx_res.0,
-                            MotionEventAction::Down,
-                            mapping.pointer_id,
+        for (action, mapping) in &active_mapping.index.single_tap {
+            let original_size: Vec2 = active_mapping.original_size.into();
+            if ineffable.just_activated(action.ineff_continuous()) {
+                let mut humanizer = settings.humanizer(action.as_ref(), mapping.humanize.as_ref());
+                if mapping.sync {
+                    // Tap down sync
+                    let original_pos = humanizer.position(mapping.position.into());
+                    ControlMsgHelper::send_touch(
+                        &cs_tx_res.0,
+                        MotionEventAction::Down,
+                        mapping.pointer_id,
+                        original_size,
+                        original_pos,
+                    );
+                    active_map.0.insert(
+                        action.to_string(),
+                        SingleTapItem {
+                            pointer_id: mapping.pointer_id,
                             original_size,
-                            mapping.position.into(),
-                        );
-                    } else {
-                        let cs_tx = cs_tx_res.0.clone();
-                        let pointer_id = mapping.pointer_id;
-                        let original_pos: Vec2 = mapping.position.into();
-                        let duration = Duration::from_millis(mapping.duration as u64);
-                        // Tap down
+                            original_pos,
+                        },
+                    );
+                } else {
+                    let cs_tx = cs_tx_res.0.clone();
+                    let pointer_id = mapping.pointer_id;
+                    let original_pos = humanizer.position(mapping.position.into());
+                    let duration = Duration::from_millis(humanizer.duration(mapping.duration));
+                    // Tap down
+                    ControlMsgHelper::send_touch(
+                        &cs_tx,
+                        MotionEventAction::Down,
+                        pointer_id,
+                        original_size,
+                        original_pos,
+                    );
+                    // wait and Tap up
+                    runtime.spawn_background_task(move |_ctx| async move {
+                        sleep(duration).await;
                         ControlMsgHelper::send_touch(
                             &cs_tx,
-                            MotionEventAction::Down,
+                            MotionEventAction::Up,
                             pointer_id,
                             original_size,
                             original_pos,
                         );
-                        // wait and Tap up
-                        runtime.spawn_background_task(move |_ctx| async move {
-                            sleep(duration).await;
-                            ControlMsgHelper::send_touch(
-                                &cs_tx,
-                                MotionEventAction::Up,
-                                pointer_id,
-                                original_size,
-                                original_pos,
-                            );
-                        });
-                    }
-                } else if mapping.sync && ineffable.just_deactivated(action.ineff_continuous()) {
-                    // Tap up sync
-                    ControlMsgHelper::send_touch(
-                        &cs_tx_res.0,
-                        MotionEventAction::Up,
-                        mapping.pointer_id,
-                        original_size,
-                        mapping.position.into(),
-                    );
+                    });
                 }
+            } else if mapping.sync && ineffable.just_deactivated(action.ineff_continuous()) {
+                active_map.release(action.as_ref(), &cs_tx_res);
             }
         }
     }
@@ -135,6 +176,7 @@ pub struct BindMappingRepeatTap {
     pub pointer_id: u64,
     pub duration: u64,
     pub interval: u32,
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
     pub input_binding: InputBinding,
 }
@@ -147,33 +189,53 @@ impl From<MappingRepeatTap> for BindMappingRepeatTap {
             pointer_id: value.pointer_id,
             duration: value.duration,
             interval: value.interval,
+            humanize: value.humanize,
             bind: value.bind.clone(),
             input_binding: ContinuousBinding::hold(value.bind).0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingRepeatTap {
     pub position: Position,
     pub note: String,
     pub pointer_id: u64,
     pub duration: u64,
     pub interval: u32,
+    /// overrides the global humanization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
 }
 
-impl ValidateMappingConfig for MappingRepeatTap {}
+impl ValidateMappingConfig for MappingRepeatTap {
+    fn validate(&self) -> Result<(), String> {
+        match &self.humanize {
+            Some(humanize) => humanize.validate(),
+            None => Ok(()),
+        }
+    }
+}
 
 #[derive(Resource, Default)]
 pub struct ActiveRepeatTapMap(HashMap<String, RepeatTapTimer>);
 
+impl ActiveRepeatTapMap {
+    // taps already sent are lifted by their own tasks
+    pub fn stop(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+}
+
 struct RepeatTapTimer {
     timer: Timer,
     pointer_id: u64,
     original_pos: Vec2,
     original_size: Vec2,
-    duration: Duration,
+    duration: u64,
+    interval: u64,
+    humanizer: Humanizer,
 }
 
 pub fn handle_repeat_tap_trigger(
@@ -184,11 +246,15 @@ pub fn handle_repeat_tap_trigger(
 ) {
     for (_, timer) in active_map.0.iter_mut() {
         if timer.timer.tick(time.delta()).just_finished() {
+            let next_interval = timer.humanizer.interval(timer.interval);
+            timer
+                .timer
+                .set_duration(Duration::from_millis(next_interval));
             let cs_tx = cs_tx_res.0.clone();
             let original_size = timer.original_size;
             let pointer_id = timer.pointer_id;
-            let original_pos = timer.original_pos;
-            let duration = timer.duration;
+            let original_pos = timer.humanizer.position(timer.original_pos);
+            let duration = Duration::from_millis(timer.humanizer.duration(timer.duration));
             // Tap down
             ControlMsgHelper::send_touch(
                 &cs_tx,
@@ -215,36 +281,36 @@ pub fn handle_repeat_tap_trigger(
 pub fn handle_repeat_tap(
     ineffable: Res<Ineffable>,
     active_mapping: Res<ActiveMappingConfig>,
+    settings: Res<MappingSettings>,
     mut active_map: ResMut<ActiveRepeatTapMap>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("RepeatTap") {
-                let mapping = mapping.as_ref_repeattap();
-                if ineffable.just_activated(action.ineff_continuous()) {
-                    let interval = Duration::from_millis(mapping.interval as u64);
-                    let original_size: Vec2 = active_mapping.original_size.into();
-                    let mut timer = Timer::new(interval, TimerMode::Repeating);
-                    timer.tick(interval);
-                    active_map.0.insert(
-                        action.to_string(),
-                        RepeatTapTimer {
-                            timer,
-                            pointer_id: mapping.pointer_id,
-                            original_pos: mapping.position.into(),
-                            original_size: original_size,
-                            duration: Duration::from_millis(mapping.duration as u64),
-                        },
-                    );
-                } else if ineffable.just_deactivated(action.ineff_continuous()) {
-                    active_map.0.remove(action.as_ref());
-                }
+        for (action, mapping) in &active_mapping.index.repeat_tap {
+            if ineffable.just_activated(action.ineff_continuous()) {
+                let interval = Duration::from_millis(mapping.interval as u64);
+                let original_size: Vec2 = active_mapping.original_size.into();
+                let mut timer = Timer::new(interval, TimerMode::Repeating);
+                timer.tick(interval);
+                active_map.0.insert(
+                    action.to_string(),
+                    RepeatTapTimer {
+                        timer,
+                        pointer_id: mapping.pointer_id,
+                        original_pos: mapping.position.into(),
+                        original_size: original_size,
+                        duration: mapping.duration,
+                        interval: mapping.interval as u64,
+                        humanizer: settings.humanizer(action.as_ref(), mapping.humanize.as_ref()),
+                    },
+                );
+            } else if ineffable.just_deactivated(action.ineff_continuous()) {
+                active_map.stop(action.as_ref());
             }
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingMultipleTapItem {
     pub position: Position,
     pub duration: u64,
@@ -256,6 +322,7 @@ pub struct BindMappingMultipleTap {
     pub note: String,
     pub pointer_id: u64,
     pub items: Vec<MappingMultipleTapItem>,
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
     pub input_binding: InputBinding,
 }
@@ -266,17 +333,21 @@ impl From<MappingMultipleTap> for BindMappingMultipleTap {
             note: value.note,
             pointer_id: value.pointer_id,
             items: value.items,
+            humanize: value.humanize,
             bind: value.bind.clone(),
             input_binding: PulseBinding::just_pressed(value.bind).0,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct MappingMultipleTap {
     pub note: String,
     pub pointer_id: u64,
     pub items: Vec<MappingMultipleTapItem>,
+    /// overrides the global humanization, `wait` varies by its interval jitter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humanize: Option<Humanize>,
     pub bind: ButtonBinding,
 }
 
@@ -285,7 +356,10 @@ impl ValidateMappingConfig for MappingMultipleTap {
         if self.items.is_empty() {
             return Err("MultipleTap's operation item list is empty".to_string());
         }
-        Ok(())
+        match &self.humanize {
+            Some(humanize) => humanize.validate(),
+            None => Ok(()),
+        }
     }
 }
 
@@ -295,37 +369,37 @@ pub fn handle_multiple_tap(
     cs_tx_res: Res<ChannelSenderCS>,
     mask_size: Res<MaskSize>,
     runtime: ResMut<TokioTasksRuntime>,
+    settings: Res<MappingSettings>,
 ) {
     if let Some(active_mapping) = &active_mapping.0 {
-        for (action, mapping) in &active_mapping.mappings {
-            if action.as_ref().starts_with("MultipleTap") {
-                let mapping = mapping.as_ref_multipletap();
-                if ineffable.just_pulsed(action.ineff_pulse()) {
-                    let cs_tx = cs_tx_res.0.clone();
-                    let original_size = mask_size.0;
-                    let pointer_id = mapping.pointer_id;
-                    let items = mapping.items.clone();
-                    runtime.spawn_background_task(move |_ctx| async move {
-                        for item in items {
-                            sleep(Duration::from_millis(item.wait)).await;
-                            ControlMsgHelper::send_touch(
-                                &cs_tx,
-                                MotionEventAction::Down,
-                                pointer_id,
-                                original_size,
-                                item.position.into(),
-                            );
-                            sleep(Duration::from_millis(item.duration)).await;
-                            ControlMsgHelper::send_touch(
-                                &cs_tx,
-                                MotionEventAction::Up,
-                                pointer_id,
-                                original_size,
-                                item.position.into(),
-                            );
-                        }
-                    });
-                }
+        for (action, mapping) in &active_mapping.index.multiple_tap {
+            if ineffable.just_pulsed(action.ineff_pulse()) {
+                let cs_tx = cs_tx_res.0.clone();
+                let original_size = mask_size.0;
+                let pointer_id = mapping.pointer_id;
+                let items = mapping.items.clone();
+                let mut humanizer = settings.humanizer(action.as_ref(), mapping.humanize.as_ref());
+                runtime.spawn_background_task(move |_ctx| async move {
+                    for item in items {
+                        let pos = humanizer.position(item.position.into());
+                        sleep(Duration::from_millis(humanizer.interval(item.wait))).await;
+                        ControlMsgHelper::send_touch(
+                            &cs_tx,
+                            MotionEventAction::Down,
+                            pointer_id,
+                            original_size,
+                            pos,
+                        );
+                        sleep(Duration::from_millis(humanizer.duration(item.duration))).await;
+                        ControlMsgHelper::send_touch(
+                            &cs_tx,
+                            MotionEventAction::Up,
+                            pointer_id,
+                            original_size,
+                            pos,
+                        );
+                    }
+                });
             }
         }
     }
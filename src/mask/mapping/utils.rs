@@ -1,18 +1,30 @@
-use std::ops::MulAssign;
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::MulAssign,
+    sync::Mutex,
+};
 
-use bevy::math::Vec2;
+use bevy::{ecs::resource::Resource, math::Vec2};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
-use crate::scrcpy::{
-    constant::{self, MotionEventAction, MotionEventButtons},
-    control_msg::ScrcpyControlMsg,
+use crate::{
+    config::{LocalConfig, ScrollForwarding},
+    scrcpy::{
+        constant::{self, MotionEventAction, MotionEventButtons},
+        control_msg::ScrcpyControlMsg,
+    },
 };
 
 pub const MIN_MOVE_STEP_LENGTH: f32 = 25.; // px
 pub const MIN_MOVE_STEP_INTERVAL: u64 = 25; // ms
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Copy, PartialEq, JsonSchema)]
 pub struct Size {
     pub width: u32,
     pub height: u32,
@@ -39,28 +51,114 @@ impl From<Vec2> for Size {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+/// How the `x` and `y` of a position are interpreted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+pub enum Anchor {
+    /// pixels in the `original_size` of the mapping config
+    #[default]
+    Absolute,
+    /// fraction of the screen size, from 0 to 1
+    Relative,
+    // offset in pixels from a point on the screen edge
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub fn is_absolute(&self) -> bool {
+        *self == Anchor::Absolute
+    }
+
+    /// Location of the anchor as a fraction of the screen size.
+    fn point(&self) -> Vec2 {
+        match self {
+            Anchor::Absolute | Anchor::Relative | Anchor::TopLeft => Vec2::new(0., 0.),
+            Anchor::Top => Vec2::new(0.5, 0.),
+            Anchor::TopRight => Vec2::new(1., 0.),
+            Anchor::Left => Vec2::new(0., 0.5),
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::Right => Vec2::new(1., 0.5),
+            Anchor::BottomLeft => Vec2::new(0., 1.),
+            Anchor::Bottom => Vec2::new(0.5, 1.),
+            Anchor::BottomRight => Vec2::new(1., 1.),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
 pub struct Position {
-    pub x: i32,
-    pub y: i32,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default, skip_serializing_if = "Anchor::is_absolute")]
+    pub anchor: Anchor,
+}
+
+impl Position {
+    /// Convert to an absolute position in the `original_size` space, which lands on the
+    /// intended point after being stretched to a `device_size` screen.
+    pub fn resolve(&mut self, original_size: Vec2, device_size: Vec2) {
+        let device_size = if device_size.x > 0. && device_size.y > 0. {
+            device_size
+        } else {
+            original_size
+        };
+        let offset = Vec2::new(self.x, self.y);
+        let pos = match self.anchor {
+            Anchor::Absolute => return,
+            Anchor::Relative => offset * original_size,
+            anchor => {
+                // keep the offset in proportion on screens of a different aspect ratio
+                let scale = (device_size / original_size).min_element();
+                let pos = anchor.point() * device_size + offset * scale;
+                pos * original_size / device_size
+            }
+        };
+        self.x = pos.x.round();
+        self.y = pos.y.round();
+        self.anchor = Anchor::Absolute;
+    }
 }
 
 impl From<(i32, i32)> for Position {
     fn from((x, y): (i32, i32)) -> Self {
-        Position { x, y }
+        Position {
+            x: x as f32,
+            y: y as f32,
+            anchor: Anchor::Absolute,
+        }
     }
 }
 
 impl From<Position> for Vec2 {
     fn from(pos: Position) -> Self {
-        Vec2::new(pos.x as f32, pos.y as f32)
+        Vec2::new(pos.x, pos.y)
     }
 }
 
+// used to migrate a mapping config to another size
 impl MulAssign<Vec2> for Position {
     fn mul_assign(&mut self, rhs: Vec2) {
-        self.x = ((self.x as f32) * rhs.x).round() as i32;
-        self.y = ((self.y as f32) * rhs.y).round() as i32;
+        match self.anchor {
+            Anchor::Absolute => {
+                self.x = (self.x * rhs.x).round();
+                self.y = (self.y * rhs.y).round();
+            }
+            // relative position doesn't depend on the size
+            Anchor::Relative => {}
+            // the anchor moves with the edge, only the offset is scaled
+            _ => {
+                let scale = rhs.min_element();
+                self.x = (self.x * scale).round();
+                self.y = (self.y * scale).round();
+            }
+        }
     }
 }
 
@@ -89,6 +187,35 @@ impl ControlMsgHelper {
             .unwrap();
     }
 
+    /// Scroll by `delta` steps at `pos`, positive to scroll right and up.
+    /// Scrcpy-server reads each axis as a 16-bit fixed-point value of `delta / 16`.
+    pub fn send_scroll(
+        cs_tx: &broadcast::Sender<ScrcpyControlMsg>,
+        size: Vec2,
+        pos: Vec2,
+        delta: Vec2,
+    ) {
+        let to_i16fp = |value: f32| -> u16 {
+            let value = (value / 16.).clamp(-1., 1.);
+            if value >= 1. {
+                i16::MAX as u16
+            } else {
+                (value * 32768.) as i16 as u16
+            }
+        };
+        cs_tx
+            .send(ScrcpyControlMsg::InjectScrollEvent {
+                x: pos.x as i32,
+                y: pos.y as i32,
+                w: size.x as u16,
+                h: size.y as u16,
+                hscroll: to_i16fp(delta.x),
+                vscroll: to_i16fp(delta.y),
+                buttons: 0,
+            })
+            .unwrap();
+    }
+
     pub fn send_keycode(
         cs_tx: &broadcast::Sender<ScrcpyControlMsg>,
         keycode: constant::Keycode,
@@ -131,3 +258,239 @@ impl ControlMsgHelper {
 pub fn ease_sigmoid_like(t: f32) -> f32 {
     1.0 / (1.0 + (-12.0 * (t - 0.5)).exp())
 }
+
+/// Maps the linear progress of a move to the eased one, both from 0 to 1.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(tag = "type")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    #[default]
+    Sigmoid,
+    /// same as the css `cubic-bezier()`, `x1` and `x2` must be in [0, 1]
+    CubicBezier {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+    },
+}
+
+impl Easing {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Easing::CubicBezier { x1, x2, .. } = self {
+            if !(0.0..=1.0).contains(x1) || !(0.0..=1.0).contains(x2) {
+                return Err(t!("mask.mapping.invalidCubicBezier", x1 => x1, x2 => x2).to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, t: f32) -> f32 {
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (2. - 2. * t).powi(3) / 2.
+                }
+            }
+            Easing::Sigmoid => ease_sigmoid_like(t),
+            Easing::CubicBezier { x1, y1, x2, y2 } => {
+                let bezier = |p1: f32, p2: f32, s: f32| {
+                    let u = 1. - s;
+                    3. * u * u * s * p1 + 3. * u * s * s * p2 + s * s * s
+                };
+                // x is monotonic in s when x1 and x2 are in [0, 1], so bisection finds it
+                let (mut low, mut high) = (0f32, 1f32);
+                for _ in 0..24 {
+                    let mid = (low + high) / 2.;
+                    if bezier(x1, x2, mid) < t {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                bezier(y1, y2, (low + high) / 2.)
+            }
+        }
+    }
+}
+
+/// Point of the Bezier curve defined by `points` at `t`, by de Casteljau's algorithm.
+pub fn bezier_point(points: &[Vec2], t: f32) -> Vec2 {
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points.windows(2).map(|w| w[0].lerp(w[1], t)).collect();
+    }
+    points[0]
+}
+
+/// Options of the local config used by mapping handlers, kept here so that handlers
+/// don't clone the whole config on every input. Refreshed by `MaskCommand::LocalConfigChange`.
+#[derive(Resource, Debug)]
+pub struct MappingSettings {
+    pub scroll_forwarding: ScrollForwarding,
+    pub humanize: Humanize,
+    pub humanize_seed: Option<u64>,
+    // triggers of each mapping since the seed was set
+    humanize_triggers: Mutex<HashMap<String, u64>>,
+}
+
+impl MappingSettings {
+    pub fn from_local_config() -> Self {
+        let config = LocalConfig::get();
+        Self {
+            scroll_forwarding: config.scroll_forwarding,
+            humanize: config.humanize,
+            humanize_seed: config.humanize_seed,
+            humanize_triggers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Random source for a trigger of the mapping `key`, using the global humanization
+    /// if the mapping has none. With a fixed seed, the variations only depend on the seed,
+    /// the mapping and how often it has been triggered, not on the order handlers run in.
+    pub fn humanizer(&self, key: &str, mapping_humanize: Option<&Humanize>) -> Humanizer {
+        let humanize = mapping_humanize.copied().unwrap_or(self.humanize);
+        let rng = match self.humanize_seed {
+            Some(seed) => {
+                let mut triggers = self.humanize_triggers.lock().unwrap();
+                let count = triggers.entry(key.to_string()).or_default();
+                *count += 1;
+                let mut hasher = DefaultHasher::new();
+                (seed, key, *count).hash(&mut hasher);
+                StdRng::seed_from_u64(hasher.finish())
+            }
+            None => StdRng::from_rng(&mut rand::rng()),
+        };
+        Humanizer { humanize, rng }
+    }
+}
+
+/// Random variation of touches, so that they don't land on the same pixel with the same timing.
+/// Each option is disabled when it is 0.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct Humanize {
+    /// max distance in px from the mapped position
+    pub position_radius: f32,
+    /// max change in ms of the touch duration
+    pub duration_jitter: u64,
+    /// max change in ms of the interval between repeated taps
+    pub interval_jitter: u64,
+    /// max distance in px of a swipe from its path, the ends are kept
+    pub path_noise: f32,
+}
+
+impl Humanize {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.position_radius < 0. || self.path_noise < 0. {
+            return Err(t!("mask.mapping.humanizeNegative").to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Humanization of a single trigger, which owns its random source.
+#[derive(Debug, Clone)]
+pub struct Humanizer {
+    humanize: Humanize,
+    rng: StdRng,
+}
+
+impl Humanizer {
+    fn random_in_circle(&mut self, radius: f32) -> Vec2 {
+        if radius <= 0. {
+            return Vec2::ZERO;
+        }
+        // sqrt keeps the points evenly spread over the area
+        let distance = radius * self.rng.random::<f32>().sqrt();
+        Vec2::from_angle(self.rng.random_range(0.0..2. * PI)) * distance
+    }
+
+    fn random_time(&mut self, value: u64, jitter: u64) -> u64 {
+        if jitter == 0 {
+            return value;
+        }
+        let offset = self.rng.random_range(-(jitter as i64)..=jitter as i64);
+        (value as i64 + offset).max(1) as u64
+    }
+
+    pub fn position(&mut self, pos: Vec2) -> Vec2 {
+        let radius = self.humanize.position_radius;
+        pos + self.random_in_circle(radius)
+    }
+
+    pub fn duration(&mut self, duration: u64) -> u64 {
+        let jitter = self.humanize.duration_jitter;
+        self.random_time(duration, jitter)
+    }
+
+    pub fn interval(&mut self, interval: u64) -> u64 {
+        let jitter = self.humanize.interval_jitter;
+        self.random_time(interval, jitter)
+    }
+
+    /// Signed bend of a swipe, drawn once so that the swipe follows a smooth curve.
+    pub fn path_bend(&mut self) -> f32 {
+        let noise = self.humanize.path_noise;
+        if noise <= 0. {
+            return 0.;
+        }
+        self.rng.random_range(-noise..=noise)
+    }
+}
+
+/// Offset of a swipe from the segment `from` to `to` at the linear progress `t`,
+/// perpendicular to the segment and fading out at both ends.
+pub fn path_offset(bend: f32, from: Vec2, to: Vec2, t: f32) -> Vec2 {
+    (to - from).normalize_or_zero().perp() * bend * (PI * t).sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(seed: Option<u64>) -> MappingSettings {
+        MappingSettings {
+            scroll_forwarding: ScrollForwarding::default(),
+            humanize: Humanize::default(),
+            humanize_seed: seed,
+            humanize_triggers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_jitter() {
+        let humanize = Humanize {
+            position_radius: 10.,
+            duration_jitter: 20,
+            interval_jitter: 20,
+            path_noise: 5.,
+        };
+        let draw = |settings: &MappingSettings| {
+            let mut humanizer = settings.humanizer("Continuous1", Some(&humanize));
+            (
+                humanizer.position(Vec2::new(100., 100.)),
+                humanizer.duration(50),
+                humanizer.interval(50),
+                humanizer.path_bend(),
+            )
+        };
+
+        let first = settings(Some(42));
+        let second = settings(Some(42));
+        // other mappings drawing in between don't change the variations
+        second
+            .humanizer("Continuous2", Some(&humanize))
+            .position(Vec2::ZERO);
+        assert_eq!(draw(&first), draw(&second));
+        assert_eq!(draw(&first), draw(&second));
+    }
+}
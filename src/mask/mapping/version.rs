@@ -0,0 +1,62 @@
+use rust_i18n::t;
+use serde_json::Value;
+
+/// Schema version of mapping config files written by this build.
+pub const MAPPING_CONFIG_VERSION: &str = "0.0.2";
+
+struct UpgradeStep {
+    from: &'static str,
+    to: &'static str,
+    upgrade: fn(&mut Value) -> Result<(), String>,
+}
+
+// register a step here for every schema change, from the previous version
+const UPGRADE_STEPS: &[UpgradeStep] = &[UpgradeStep {
+    from: "0.0.1",
+    to: "0.0.2",
+    upgrade: upgrade_0_0_1,
+}];
+
+/// 0.0.2 adds mapping layers and anchored positions, both optional.
+fn upgrade_0_0_1(_config: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+/// Upgrade a raw mapping config to `MAPPING_CONFIG_VERSION` step by step.
+/// Returns the migrations that ran, e.g. `0.0.1 -> 0.0.2`.
+pub fn upgrade_mapping_config(config: &mut Value) -> Result<Vec<String>, String> {
+    let mut migrations = Vec::<String>::new();
+    loop {
+        let version = config
+            .get("version")
+            .and_then(Value::as_str)
+            .ok_or_else(|| t!("mask.mapping.mappingVersionMissing").to_string())?
+            .to_string();
+        if version == MAPPING_CONFIG_VERSION {
+            return Ok(migrations);
+        }
+
+        let step = UPGRADE_STEPS
+            .iter()
+            .find(|step| step.from == version)
+            .ok_or_else(|| {
+                t!(
+                    "mask.mapping.mappingVersionUnsupported",
+                    version => version,
+                    current => MAPPING_CONFIG_VERSION
+                )
+                .to_string()
+            })?;
+        (step.upgrade)(config).map_err(|e| {
+            format!(
+                "{} {} -> {}: {}",
+                t!("mask.mapping.mappingUpgradeFailed"),
+                step.from,
+                step.to,
+                e
+            )
+        })?;
+        config["version"] = Value::from(step.to);
+        migrations.push(format!("{} -> {}", step.from, step.to));
+    }
+}
@@ -1,15 +1,16 @@
 use bevy::{prelude::*, window::WindowLevel};
-use bevy_ineffable::prelude::IneffableCommands;
 use rust_i18n::t;
 
 use crate::{
     mask::mapping::{
         MappingState,
         config::{
-            ActiveMappingConfig, MappingConfig, load_mapping_config, validate_mapping_config,
+            ActiveMappingConfig, MappingConfig, apply_mapping_input, load_mapping_config,
+            validate_mapping_config,
         },
         cursor::{CursorPosition, CursorState},
         script_helper::ScriptAST,
+        utils::{MappingSettings, Size},
     },
     utils::{ChannelReceiverM, ChannelSenderCS},
 };
@@ -21,6 +22,7 @@ pub enum MaskCommand {
         top: i32,
         right: i32,
         bottom: i32,
+        device_size: (u32, u32),
     },
     WinSwitchLevel {
         top: bool,
@@ -38,11 +40,17 @@ pub enum MaskCommand {
     EvalScript {
         script: String,
     },
+    // options of the local config used by mapping handlers are changed
+    LocalConfigChange,
 }
 
 #[derive(Resource)]
 pub struct MaskSize(pub Vec2);
 
+/// Size of the main device, used to resolve relative and anchored mapping positions.
+#[derive(Resource, Default)]
+pub struct DeviceSize(pub Size);
+
 pub fn handle_mask_command(
     m_rx: Res<ChannelReceiverM>,
     cs_tx_res: Res<ChannelSenderCS>,
@@ -50,9 +58,11 @@ pub fn handle_mask_command(
     mut window: Single<&mut Window>,
     mut next_mapping_state: ResMut<NextState<MappingState>>,
     mut next_cursor_state: ResMut<NextState<CursorState>>,
-    mut ineffable: IneffableCommands,
+    mut commands: Commands,
     mut active_mapping: ResMut<ActiveMappingConfig>,
     mut mask_size: ResMut<MaskSize>,
+    mut device_size: ResMut<DeviceSize>,
+    mut mapping_settings: ResMut<MappingSettings>,
 ) {
     for (msg, oneshot_tx) in m_rx.0.try_iter() {
         match msg {
@@ -61,6 +71,7 @@ pub fn handle_mask_command(
                 top,
                 right,
                 bottom,
+                device_size: new_device_size,
             } => {
                 // logical size and position
                 let width = (right - left) as f32;
@@ -81,6 +92,22 @@ pub fn handle_mask_command(
                 .to_string();
 
                 log::info!("[Mask] {}", msg);
+
+                // positions of the active mapping have to be resolved again
+                let new_device_size: Size = new_device_size.into();
+                if device_size.0 != new_device_size {
+                    device_size.0 = new_device_size;
+                    if active_mapping.0.is_some() {
+                        match load_mapping_config(&active_mapping.1, device_size.0) {
+                            Ok(mapping_config) => {
+                                active_mapping.0 = Some(mapping_config);
+                                commands.queue(apply_mapping_input);
+                            }
+                            Err(e) => log::error!("[Mapping] {}", e),
+                        }
+                    }
+                }
+
                 oneshot_tx.send(Ok(msg)).unwrap();
             }
             MaskCommand::WinSwitchLevel { top } => {
@@ -109,6 +136,10 @@ pub fn handle_mask_command(
                 log::info!("[Mask] {}", msg);
                 oneshot_tx.send(Ok(msg)).unwrap();
             }
+            MaskCommand::LocalConfigChange => {
+                *mapping_settings = MappingSettings::from_local_config();
+                oneshot_tx.send(Ok(String::new())).unwrap();
+            }
             MaskCommand::GetActiveMapping => {
                 oneshot_tx.send(Ok(active_mapping.1.clone())).unwrap();
             }
@@ -128,10 +159,10 @@ pub fn handle_mask_command(
                     t!("mask.loadActivateMappingConfig"),
                     file_name
                 );
-                match load_mapping_config(&file_name) {
-                    Ok((mapping_config, input_config)) => {
-                        ineffable.set_config(&input_config);
+                match load_mapping_config(&file_name, device_size.0) {
+                    Ok(mapping_config) => {
                         active_mapping.0 = Some(mapping_config);
+                        commands.queue(apply_mapping_input);
                         active_mapping.1 = file_name;
                         oneshot_tx.send(Ok(String::new())).unwrap();
                     }
@@ -10,7 +10,7 @@ use bevy::{
 };
 
 use crate::mask::{
-    mask_command::{MaskSize, handle_mask_command},
+    mask_command::{DeviceSize, MaskSize, handle_mask_command},
     video::{VideoAttributes, handle_video_msg, init_video},
 };
 
@@ -20,6 +20,7 @@ impl Plugin for MaskPlugins {
     fn build(&self, app: &mut App) {
         app.add_plugins((ui::UiPlugins, mapping::MappingPlugins))
             .init_non_send_resource::<VideoAttributes>()
+            .init_resource::<DeviceSize>()
             .add_systems(Startup, (init_mask_size, init_video))
             .add_systems(Update, (handle_mask_command, handle_video_msg));
     }
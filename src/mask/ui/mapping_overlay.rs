@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use crate::mask::mapping::config::ActiveMappingConfig;
+
+/// Briefly show the name and active layers of the mapping after it is switched.
+pub struct MappingOverlayPlugin;
+
+impl Plugin for MappingOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                show_mapping_overlay.run_if(resource_changed::<ActiveMappingConfig>),
+                fade_mapping_overlay,
+            ),
+        );
+    }
+}
+
+const OVERLAY_SECONDS: f32 = 1.5;
+
+#[derive(Component)]
+struct MappingOverlay(Timer);
+
+fn show_mapping_overlay(
+    mut commands: Commands,
+    query: Query<Entity, With<MappingOverlay>>,
+    active_mapping: Res<ActiveMappingConfig>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(config) = &active_mapping.0 else {
+        return;
+    };
+    let mut name = active_mapping.1.trim_end_matches(".json").to_string();
+    let layers: Vec<&str> = config
+        .layers
+        .iter()
+        .filter(|layer| config.active_layers.contains(&layer.name))
+        .map(|layer| layer.name.as_str())
+        .collect();
+    if !layers.is_empty() {
+        name = format!("{} [{}]", name, layers.join(", "));
+    }
+
+    commands.spawn((
+        MappingOverlay(Timer::from_seconds(OVERLAY_SECONDS, TimerMode::Once)),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Percent(10.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Node {
+                padding: UiRect::px(12., 12., 6., 6.),
+                ..default()
+            },
+            BorderRadius::all(Val::Px(5.)),
+            BackgroundColor(Color::linear_rgba(0., 0., 0., 0.6)),
+            children![(
+                Text::new(name),
+                TextFont {
+                    font_size: 20.,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            )],
+        )],
+    ));
+}
+
+fn fade_mapping_overlay(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut MappingOverlay)>,
+) {
+    for (entity, mut overlay) in query.iter_mut() {
+        if overlay.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -1,15 +1,16 @@
 pub mod basic;
 pub mod mapping_label;
+pub mod mapping_overlay;
 
 use basic::BasicPlugin;
 use bevy::app::{App, Plugin};
 
-use crate::mask::ui::mapping_label::MappingLabelPlugin;
+use crate::mask::ui::{mapping_label::MappingLabelPlugin, mapping_overlay::MappingOverlayPlugin};
 
 pub struct UiPlugins;
 
 impl Plugin for UiPlugins {
     fn build(&self, app: &mut App) {
-        app.add_plugins((BasicPlugin, MappingLabelPlugin));
+        app.add_plugins((BasicPlugin, MappingLabelPlugin, MappingOverlayPlugin));
     }
 }
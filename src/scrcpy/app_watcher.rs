@@ -0,0 +1,218 @@
+use std::{fs, io::Cursor, time::Duration};
+
+use bevy::log;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{broadcast, oneshot},
+    time::sleep,
+};
+
+use crate::{
+    mask::mask_command::MaskCommand,
+    scrcpy::adb::Device,
+    utils::{is_safe_file_name, relate_to_data_path, share::ControlledDevice},
+    web::ws::WebSocketNotification,
+};
+
+const RULES_FILE: &str = "app_mapping_rules.json";
+
+/// Rules stored in `app_mapping_rules.json` of the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppMappingRules {
+    pub enable: bool,
+    pub interval_ms: u64,
+    pub rules: Vec<AppMappingRule>,
+}
+
+impl Default for AppMappingRules {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            interval_ms: 2000,
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppMappingRule {
+    pub package: String,
+    // full activity name, match any activity of the package if None
+    #[serde(default)]
+    pub activity: Option<String>,
+    pub mapping_file: String,
+}
+
+impl AppMappingRules {
+    /// Rules are read on every poll, so the file can be edited while running.
+    pub fn load() -> Result<Self, String> {
+        let path = relate_to_data_path([RULES_FILE]);
+        if !path.exists() {
+            return Ok(Self {
+                enable: false,
+                ..Default::default()
+            });
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", t!("scrcpy.readAppRulesFailed"), e))?;
+        let rules: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("{}: {}", t!("scrcpy.parseAppRulesFailed"), e))?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    pub fn exists() -> bool {
+        relate_to_data_path([RULES_FILE]).exists()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        self.validate()?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("{}: {}", t!("scrcpy.parseAppRulesFailed"), e))?;
+        fs::write(relate_to_data_path([RULES_FILE]), content)
+            .map_err(|e| format!("{}: {}", t!("scrcpy.writeAppRulesFailed"), e))
+    }
+
+    // mapping files are joined to the data path, so don't let them escape it
+    fn validate(&self) -> Result<(), String> {
+        match self
+            .rules
+            .iter()
+            .find(|rule| !is_safe_file_name(&rule.mapping_file))
+        {
+            Some(rule) => Err(format!(
+                "{}: {}",
+                t!("scrcpy.appRuleFileNotSafe"),
+                rule.mapping_file
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn find(&self, package: &str, activity: &str) -> Option<&AppMappingRule> {
+        self.rules.iter().find(|rule| {
+            rule.package == package
+                && rule
+                    .activity
+                    .as_ref()
+                    .is_none_or(|target| target == activity)
+        })
+    }
+}
+
+/// Get (package, activity) of the resumed activity on device.
+fn foreground_app(device_id: &str) -> Result<(String, String), String> {
+    let mut output: Vec<u8> = Vec::new();
+    let mut cursor = Cursor::new(&mut output);
+    Device::shell(
+        device_id,
+        ["dumpsys", "activity", "activities"],
+        &mut cursor,
+    )?;
+
+    let stdout = String::from_utf8_lossy(&output);
+    for line in stdout.lines() {
+        let line = line.trim();
+        // e.g. mResumedActivity: ActivityRecord{1a2b3c u0 com.example/.MainActivity t42}
+        if !(line.starts_with("mResumedActivity") || line.starts_with("topResumedActivity")) {
+            continue;
+        }
+        if let Some(component) = line.split_whitespace().find(|s| s.contains('/')) {
+            let (package, activity) = component.split_once('/').unwrap();
+            let activity = activity.trim_end_matches('}');
+            let activity = if activity.starts_with('.') {
+                format!("{}{}", package, activity)
+            } else {
+                activity.to_string()
+            };
+            return Ok((package.to_string(), activity));
+        }
+    }
+    Err(t!("scrcpy.foregroundAppNotFound").to_string())
+}
+
+/// Poll the foreground app of main device and activate the matching mapping.
+pub async fn watch_foreground_app(
+    m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+    ws_tx: broadcast::Sender<WebSocketNotification>,
+) {
+    let mut last_app: Option<(String, String)> = None;
+    loop {
+        let rules = match AppMappingRules::load() {
+            Ok(rules) => rules,
+            Err(e) => {
+                log::warn!("[Controller] {}", e);
+                AppMappingRules {
+                    enable: false,
+                    ..Default::default()
+                }
+            }
+        };
+        sleep(Duration::from_millis(rules.interval_ms.max(500))).await;
+        if !rules.enable || rules.rules.is_empty() {
+            continue;
+        }
+
+        let Some(main_device) = ControlledDevice::get_main_device().await else {
+            last_app = None;
+            continue;
+        };
+        let device_id = main_device.device_id.clone();
+        let (package, activity) =
+            match tokio::task::spawn_blocking(move || foreground_app(&device_id)).await {
+                Ok(Ok(app)) => app,
+                Ok(Err(e)) => {
+                    log::debug!("[Controller] {}", e);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+
+        let current_app = Some((package.clone(), activity.clone()));
+        if last_app == current_app {
+            continue;
+        }
+
+        let Some(rule) = rules.find(&package, &activity) else {
+            last_app = current_app;
+            continue;
+        };
+        let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+        m_tx.send((
+            MaskCommand::LoadAndActivateMappingConfig {
+                file_name: rule.mapping_file.clone(),
+            },
+            oneshot_tx,
+        ))
+        .unwrap();
+        match oneshot_rx.await.unwrap() {
+            Ok(_) => {
+                // only remember the app once its mapping is loaded, so a failed load is retried
+                last_app = current_app;
+                log::info!(
+                    "[Controller] {}",
+                    t!(
+                        "scrcpy.appMappingActivated",
+                        package => package,
+                        file => rule.mapping_file
+                    )
+                );
+                ws_tx
+                    .send(WebSocketNotification::AppMappingSwitched {
+                        package,
+                        activity,
+                        file: rule.mapping_file.clone(),
+                    })
+                    .ok();
+            }
+            Err(e) => log::warn!(
+                "[Controller] {}: {}. {}",
+                t!("scrcpy.appMappingFailed"),
+                rule.mapping_file,
+                e
+            ),
+        }
+    }
+}
@@ -10,14 +10,15 @@ use tokio::{
     },
     sync::{
         broadcast::{self, error::RecvError},
-        mpsc::UnboundedSender,
+        mpsc::{self, UnboundedSender},
         oneshot, watch,
     },
-    time::timeout,
+    time::{Instant, sleep_until, timeout},
 };
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    config::LocalConfig,
     mask::mask_command::MaskCommand,
     scrcpy::{
         control_msg::{ScrcpyControlMsg, ScrcpyDeviceMsg},
@@ -74,70 +75,92 @@ impl ScrcpyConnection {
         token: CancellationToken,
         mut cs_rx: broadcast::Receiver<ScrcpyControlMsg>,
         mut watch_rx: watch::Receiver<(u32, u32)>,
+        device_id: String,
     ) {
-        tokio::select! {
-            _ = token.cancelled()=>{
-                log::info!("[Controller] {}", t!("scrcpy.controlConnectionCancelled"));
-            }
-            _ = async {
-                loop {
-                    match cs_rx.recv().await {
-                        Ok(mut msg) => {
-                                // scale position
-                                match &mut msg {
-                                    ScrcpyControlMsg::InjectTouchEvent {
-                                        x,
-                                        y,
-                                        w,
-                                        h,
-                                        action: _,
-                                        pointer_id: _,
-                                        pressure: _,
-                                        action_button: _,
-                                        buttons: _,
-                                    } => {
-                                        let (device_w, device_h) = watch_rx.borrow_and_update().clone();
-                                        let (old_x, old_y) = (*x, *y);
-                                        let (old_w, old_h) = (*w, *h);
-                                        *x = old_x * device_w as i32 / old_w as i32;
-                                        *y = old_y * device_h as i32 / old_h as i32;
-                                        *w = device_w as u16;
-                                        *h = device_h as u16;
-                                    }
-                                    ScrcpyControlMsg::InjectScrollEvent {
-                                        x,
-                                        y,
-                                        w,
-                                        h,
-                                        hscroll: _,
-                                        vscroll: _,
-                                        buttons: _,
-                                    } => {
-                                        let (device_w, device_h) = watch_rx.borrow_and_update().clone();
-                                        let (old_x, old_y) = (*x, *y);
-                                        let (old_w, old_h) = (*w, *h);
-                                        *x = old_x * device_w as i32 / old_w as i32;
-                                        *y = old_y * device_h as i32 / old_h as i32;
-                                        *w = device_w as u16;
-                                        *h = device_h as u16;
-                                    }
-                                    _ => {}
-                                };
-                                let data:Vec<u8> = msg.into();
-                                if let Err(e) = write_half.write_all(&data).await {
-                                    log::error!("[Controller] {}: {}", t!("scrcpy.controlConnWriteFailed"),e);
-                                }
-                        }
-                        Err(RecvError::Lagged(skipped)) => {
-                            log::warn!("[Controller] {}",t!("controller.csReceiverLagged", skipped => skipped));
-                        }
-                        Err(e) => {
-                            log::info!("[Controller] {}: {}", t!("scrcpy.controlChannelClosed"),e);
+        // messages are stamped on arrival and queued with the time to send them,
+        // so the writer only waits for what is left of each delay
+        let (delay_tx, mut delay_rx) = mpsc::unbounded_channel::<(Instant, ScrcpyControlMsg)>();
+        let receive = async move {
+            loop {
+                match cs_rx.recv().await {
+                    Ok(mut msg) => {
+                        let received = Instant::now();
+                        // read every time so that calibration can be adjusted while connected
+                        let calibration = LocalConfig::get_device_calibration(&device_id);
+                        // scale position
+                        match &mut msg {
+                            ScrcpyControlMsg::InjectTouchEvent {
+                                x,
+                                y,
+                                w,
+                                h,
+                                action: _,
+                                pointer_id: _,
+                                pressure: _,
+                                action_button: _,
+                                buttons: _,
+                            } => {
+                                let (device_w, device_h) = watch_rx.borrow_and_update().clone();
+                                (*x, *y) =
+                                    calibration.transform((*x, *y), (*w, *h), (device_w, device_h));
+                                *w = device_w as u16;
+                                *h = device_h as u16;
+                            }
+                            ScrcpyControlMsg::InjectScrollEvent {
+                                x,
+                                y,
+                                w,
+                                h,
+                                hscroll: _,
+                                vscroll: _,
+                                buttons: _,
+                            } => {
+                                let (device_w, device_h) = watch_rx.borrow_and_update().clone();
+                                (*x, *y) =
+                                    calibration.transform((*x, *y), (*w, *h), (device_w, device_h));
+                                *w = device_w as u16;
+                                *h = device_h as u16;
+                            }
+                            _ => {}
+                        };
+                        let send_at = received + Duration::from_millis(calibration.delay_ms);
+                        if delay_tx.send((send_at, msg)).is_err() {
                             break;
                         }
                     }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!(
+                            "[Controller] {}",
+                            t!("controller.csReceiverLagged", skipped => skipped)
+                        );
+                    }
+                    Err(e) => {
+                        log::info!("[Controller] {}: {}", t!("scrcpy.controlChannelClosed"), e);
+                        break;
+                    }
                 }
-            }=>{
+            }
+        };
+        let write = async {
+            // ends once the receiving side is done and the queue is drained
+            while let Some((send_at, msg)) = delay_rx.recv().await {
+                sleep_until(send_at).await;
+                let data: Vec<u8> = msg.into();
+                if let Err(e) = write_half.write_all(&data).await {
+                    log::error!(
+                        "[Controller] {}: {}",
+                        t!("scrcpy.controlConnWriteFailed"),
+                        e
+                    );
+                }
+            }
+        };
+
+        tokio::select! {
+            _ = token.cancelled()=>{
+                log::info!("[Controller] {}", t!("scrcpy.controlConnectionCancelled"));
+            }
+            _ = async { tokio::join!(receive, write) } =>{
                 log::error!("[Controller] {}", t!("scrcpy.controlCnnShutdownUnexpectedly"));
             }
         }
@@ -151,7 +174,7 @@ impl ScrcpyConnection {
         cr_tx: UnboundedSender<ScrcpyDeviceMsg>,
         watch_tx: watch::Sender<(u32, u32)>,
         scid: &str,
-        main: bool,
+        role_rx: watch::Receiver<bool>,
     ) {
         loop {
             match ScrcpyDeviceMsg::read_msg(&mut read_half, scid.to_string()).await {
@@ -167,7 +190,7 @@ impl ScrcpyConnection {
                         watch_tx.send((width, height)).unwrap();
                     }
                     // only forward other message from main device
-                    if main {
+                    if *role_rx.borrow() {
                         cr_tx.send(msg).unwrap();
                     }
                 }
@@ -185,13 +208,13 @@ impl ScrcpyConnection {
         cr_tx: UnboundedSender<ScrcpyDeviceMsg>,
         watch_tx: watch::Sender<(u32, u32)>,
         scid: &str,
-        main: bool,
+        role_rx: watch::Receiver<bool>,
     ) {
         tokio::select! {
             _ = token.cancelled()=>{
                 log::info!("[Controller] {}", t!("scrcpy.controlConnectionReaderCancelled"));
             }
-            _ = Self::control_reader_handler(read_half, cr_tx, watch_tx, scid, main)=>{
+            _ = Self::control_reader_handler(read_half, cr_tx, watch_tx, scid, role_rx)=>{
                 log::error!("[Controller] {}", t!("scrcpy.controlReadShutdownUnexpectedly"));
             }
         }
@@ -204,7 +227,7 @@ impl ScrcpyConnection {
         cr_tx: UnboundedSender<ScrcpyDeviceMsg>,
         m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
         scid: String,
-        main: bool,
+        role_rx: watch::Receiver<bool>,
         token: CancellationToken,
         meta_flag: bool,
     ) {
@@ -221,7 +244,11 @@ impl ScrcpyConnection {
         let finnal_token = token.clone();
         let token_copy = token.clone();
         let (watch_tx, watch_rx) = watch::channel::<(u32, u32)>((0, 0)); // share device size with writer
-        if main {
+        let device_id = ControlledDevice::get_device(&scid)
+            .await
+            .map(|device| device.device_id)
+            .unwrap_or_default();
+        if *role_rx.borrow() {
             let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
             m_tx.send((
                 MaskCommand::DeviceConnectionChange { connect: true },
@@ -232,12 +259,13 @@ impl ScrcpyConnection {
         }
 
         tokio::select! {
-            _ = Self::control_writer(write_half, token, cs_rx, watch_rx) => {finnal_token.cancel();}
-            _ = Self::control_reader(read_half, token_copy, cr_tx, watch_tx, &scid, main) => {finnal_token.cancel();}
+            _ = Self::control_writer(write_half, token, cs_rx, watch_rx, device_id) => {finnal_token.cancel();}
+            _ = Self::control_reader(read_half, token_copy, cr_tx, watch_tx, &scid, role_rx.clone()) => {finnal_token.cancel();}
         }
 
         log::info!("[Controller] {}", t!("scrcpy.controlConnectionClosed"));
-        if main {
+        // the role may have been switched while connected
+        if *role_rx.borrow() {
             let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
             m_tx.send((
                 MaskCommand::DeviceConnectionChange { connect: false },
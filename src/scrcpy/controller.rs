@@ -8,7 +8,7 @@ use tokio::{
     sync::{
         broadcast,
         mpsc::{self, UnboundedReceiver},
-        oneshot,
+        oneshot, watch,
     },
 };
 use tokio_util::sync::CancellationToken;
@@ -17,11 +17,12 @@ use crate::{
     config::LocalConfig,
     mask::mask_command::MaskCommand,
     scrcpy::{
+        app_watcher,
         connection::ScrcpyConnection,
         control_msg::{ScrcpyControlMsg, ScrcpyDeviceMsg},
         media::VideoMsg,
     },
-    utils::{mask_win_move_helper, share::ControlledDevice},
+    utils::{mask_win_move_helper, rotation_mapping_helper, share::ControlledDevice},
     web::ws::WebSocketNotification,
 };
 
@@ -32,6 +33,8 @@ pub enum ControllerCommand {
     ConnectSubControl(String),
     ShutdownMain(String),
     ShutdownSub(String),
+    DemoteMain(String),
+    PromoteSub(String),
 }
 
 pub struct Controller;
@@ -61,6 +64,8 @@ impl Controller {
         m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
         ws_tx: broadcast::Sender<WebSocketNotification>,
     ) {
+        // mapping file last activated by rotation of each device, by scid
+        let mut last_rotation_files: HashMap<String, String> = HashMap::new();
         loop {
             match cr_rx.recv().await {
                 Some(msg) => match msg {
@@ -106,6 +111,25 @@ impl Controller {
                             ),
                             msg
                         );
+
+                        match rotation_mapping_helper(
+                            &scid,
+                            rotation,
+                            width,
+                            height,
+                            &mut last_rotation_files,
+                            &m_tx,
+                        )
+                        .await
+                        {
+                            Ok(Some(file)) => log::info!(
+                                "[Controller] {}: {}",
+                                t!("scrcpy.rotationMappingActivated"),
+                                file
+                            ),
+                            Ok(None) => {}
+                            Err(e) => log::warn!("[Controller] {}", e),
+                        }
                     }
                     ScrcpyDeviceMsg::Unknown => {
                         log::warn!("[Controller] {}", t!("scrcpy.unknownControlMsg"))
@@ -136,9 +160,16 @@ impl Controller {
         let ws_tx_copy = ws_tx.clone();
         tokio::spawn(async move { Self::cr_msg_handler(cr_rx, m_tx_copy, ws_tx_copy).await });
 
+        // foreground app watcher for switching mapping
+        let m_tx_copy = m_tx.clone();
+        let ws_tx_copy = ws_tx.clone();
+        tokio::spawn(async move { app_watcher::watch_foreground_app(m_tx_copy, ws_tx_copy).await });
+
         // receive command from web server to accept and shutdown scrcpy connection
         log::info!("[Controller] {}", t!("scrcpy.startReceiveCommand"));
         let mut signal_map: HashMap<String, CancellationToken> = HashMap::new();
+        // whether the control connection of scid is main, can be switched while connected
+        let mut role_map: HashMap<String, watch::Sender<bool>> = HashMap::new();
         loop {
             match d_rx.recv().await {
                 Some(cmd) => match cmd {
@@ -151,6 +182,8 @@ impl Controller {
 
                         let token = CancellationToken::new();
                         signal_map.insert(socket_id.clone(), token.clone());
+                        let (role_tx, role_rx) = watch::channel(true);
+                        role_map.insert(scid.clone(), role_tx);
 
                         log::info!(
                             "[Controller] {}: {}",
@@ -174,7 +207,7 @@ impl Controller {
                                 tokio::spawn(async move {
                                     ScrcpyConnection::new(socket)
                                         .handle_control(
-                                            cs_rx, cr_tx_copy, m_tx_copy, scid, true, token,
+                                            cs_rx, cr_tx_copy, m_tx_copy, scid, role_rx, token,
                                             meta_flag,
                                         )
                                         .await;
@@ -195,6 +228,7 @@ impl Controller {
                                 );
                                 ControlledDevice::remove_device(&scid).await;
                                 signal_map.remove(&socket_id);
+                                role_map.remove(&scid);
                             }
                         }
                     }
@@ -244,6 +278,8 @@ impl Controller {
 
                         let token = CancellationToken::new();
                         signal_map.insert(socket_id.clone(), token.clone());
+                        let (role_tx, role_rx) = watch::channel(false);
+                        role_map.insert(scid.clone(), role_tx);
 
                         log::info!("[Controller] {}: {}", t!("scrcpy.creatingSubControl"), scid);
                         let sc_rx = cs_tx.subscribe();
@@ -263,7 +299,8 @@ impl Controller {
                                 tokio::spawn(async move {
                                     ScrcpyConnection::new(socket)
                                         .handle_control(
-                                            sc_rx, cr_tx_copy, m_tx_copy, scid, false, token, true,
+                                            sc_rx, cr_tx_copy, m_tx_copy, scid, role_rx, token,
+                                            true,
                                         )
                                         .await;
                                     ws_tx_copy
@@ -283,6 +320,7 @@ impl Controller {
                                 );
                                 ControlledDevice::remove_device(&scid).await;
                                 signal_map.remove(&socket_id);
+                                role_map.remove(&scid);
                             }
                         }
                     }
@@ -301,6 +339,7 @@ impl Controller {
                                 token.cancel();
                             }
                             signal_map.clear();
+                            role_map.clear();
                         }
                     }
                     ControllerCommand::ShutdownSub(scid) => {
@@ -317,6 +356,38 @@ impl Controller {
                                 token.cancel();
                                 signal_map.remove(&socket_id);
                             }
+                            role_map.remove(&scid);
+                        }
+                    }
+                    ControllerCommand::DemoteMain(scid) => {
+                        let Some(token) = signal_map.remove("main_control") else {
+                            log::warn!("[Controller] {}", t!("scrcpy.mainConnectionNotExist"));
+                            continue;
+                        };
+                        log::info!("[Controller] {}: {}", t!("scrcpy.demoteMain"), scid);
+                        // video only belongs to main device
+                        if let Some(video_token) = signal_map.remove("main_video") {
+                            video_token.cancel();
+                        }
+                        signal_map.insert(format!("sub_control_{}", scid), token);
+                        if let Some(role_tx) = role_map.get(&scid) {
+                            role_tx.send(false).ok();
+                        }
+                    }
+                    ControllerCommand::PromoteSub(scid) => {
+                        let socket_id = format!("sub_control_{}", scid);
+                        let Some(token) = signal_map.remove(&socket_id) else {
+                            log::warn!(
+                                "[Controller] {}: {}",
+                                t!("scrcpy.subConnectionNotExist"),
+                                socket_id
+                            );
+                            continue;
+                        };
+                        log::info!("[Controller] {}: {}", t!("scrcpy.promoteSub"), scid);
+                        signal_map.insert("main_control".to_string(), token);
+                        if let Some(role_tx) = role_map.get(&scid) {
+                            role_tx.send(true).ok();
                         }
                     }
                 },
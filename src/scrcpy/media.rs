@@ -3,6 +3,7 @@ use std::{fmt, io::Write};
 use bevy::ecs::error::Result;
 use ffmpeg_next::{Packet, codec, decoder, format::Pixel, frame, packet, software::scaling};
 use rust_i18n::t;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::{io::AsyncReadExt, net::TcpStream};
 
@@ -89,7 +90,7 @@ impl PacketMerger {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub enum VideoCodec {
     H264,
     H265,
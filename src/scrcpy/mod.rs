@@ -1,6 +1,7 @@
 use serde::Serialize;
 
 pub mod adb;
+pub mod app_watcher;
 pub mod connection;
 pub mod constant;
 pub mod control_msg;
@@ -14,17 +15,28 @@ pub struct ScrcpyDevice {
     pub socket_ids: Vec<String>,
     pub name: String,
     pub main: bool,
+    pub video: bool,
+    pub display_id: i32,
     pub device_size: (u32, u32),
 }
 
 impl ScrcpyDevice {
-    pub fn new(device_id: String, scid: String, main: bool, socket_ids: Vec<String>) -> Self {
+    pub fn new(
+        device_id: String,
+        scid: String,
+        main: bool,
+        video: bool,
+        display_id: i32,
+        socket_ids: Vec<String>,
+    ) -> Self {
         Self {
             device_id,
             scid,
             socket_ids,
             name: "Unknow".to_string(),
             main,
+            video,
+            display_id,
             device_size: (0, 0),
         }
     }
@@ -1,6 +1,7 @@
 pub mod share;
 
 use std::{
+    collections::HashMap,
     env,
     path::{Path, PathBuf},
 };
@@ -17,7 +18,7 @@ use crate::{
     config::LocalConfig,
     mask::mask_command::MaskCommand,
     scrcpy::{control_msg::ScrcpyControlMsg, media::VideoMsg},
-    utils::share::UpdateInfo,
+    utils::share::{ControlledDevice, UpdateInfo},
 };
 
 pub const IDENTIFIER: &str = "com.akichase.scrcpy-mask";
@@ -86,7 +87,11 @@ pub async fn mask_win_move_helper(
     device_h: u32,
     m_tx: &crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
 ) -> String {
-    let config = LocalConfig::get();
+    // use the profile of main device if exists
+    let config = match ControlledDevice::get_main_device().await {
+        Some(device) => LocalConfig::get_for_device(&device.device_id),
+        None => LocalConfig::get(),
+    };
     let (left, top, right, bottom) = {
         if device_w >= device_h {
             // horizontal
@@ -111,6 +116,7 @@ pub async fn mask_win_move_helper(
             top,
             right,
             bottom,
+            device_size: (device_w, device_h),
         },
         oneshot_tx,
     ))
@@ -118,6 +124,83 @@ pub async fn mask_win_move_helper(
     oneshot_rx.await.unwrap().unwrap()
 }
 
+/// Load and activate the mapping file specified in the device profile.
+/// Returns the activated file name, or `None` if the profile doesn't override it.
+pub async fn profile_mapping_helper(
+    device_id: &str,
+    m_tx: &crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+) -> Result<Option<String>, String> {
+    let Some(file_name) =
+        LocalConfig::get_device_profile(device_id).and_then(|profile| profile.active_mapping_file)
+    else {
+        return Ok(None);
+    };
+
+    let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+    m_tx.send((
+        MaskCommand::LoadAndActivateMappingConfig {
+            file_name: file_name.clone(),
+        },
+        oneshot_tx,
+    ))
+    .unwrap();
+    oneshot_rx
+        .await
+        .unwrap()
+        .map(|_| Some(file_name.clone()))
+        .map_err(|e| format!("{}: {}. {}", t!("utils.profileMappingFailed"), file_name, e))
+}
+
+/// Load and activate the mapping file set for the orientation of the device.
+/// Returns `None` if no file is set, or if the file is already the last one activated
+/// for the device, which `last_files` keeps by scid.
+pub async fn rotation_mapping_helper(
+    scid: &str,
+    rotation: u16,
+    width: u32,
+    height: u32,
+    last_files: &mut HashMap<String, String>,
+    m_tx: &crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+) -> Result<Option<String>, String> {
+    let config = match ControlledDevice::get_device(scid).await {
+        Some(device) => LocalConfig::get_for_device(&device.device_id),
+        None => LocalConfig::get(),
+    };
+    let Some(file_name) = config
+        .rotation_mapping
+        .resolve(rotation, width, height)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+    if last_files.get(scid) == Some(&file_name) {
+        return Ok(None);
+    }
+    let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+    m_tx.send((
+        MaskCommand::LoadAndActivateMappingConfig {
+            file_name: file_name.clone(),
+        },
+        oneshot_tx,
+    ))
+    .unwrap();
+    oneshot_rx
+        .await
+        .unwrap()
+        .map(|_| {
+            last_files.insert(scid.to_string(), file_name.clone());
+            Some(file_name.clone())
+        })
+        .map_err(|e| {
+            format!(
+                "{}: {}. {}",
+                t!("utils.rotationMappingFailed"),
+                file_name,
+                e
+            )
+        })
+}
+
 const UPDATE_URL: &str = "https://api.github.com/repos/AkiChase/scrcpy-mask/releases/latest";
 const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -19,6 +19,14 @@ impl ControlledDevice {
         device_list.iter().find(|device| device.main).cloned()
     }
 
+    pub async fn get_device(scid: &str) -> Option<ScrcpyDevice> {
+        let device_list = CONTROLLED_DEVICES.read().await;
+        device_list
+            .iter()
+            .find(|device| device.scid == scid)
+            .cloned()
+    }
+
     pub async fn is_any_device_controlled() -> bool {
         let device_list = CONTROLLED_DEVICES.read().await;
         !device_list.is_empty()
@@ -34,9 +42,44 @@ impl ControlledDevice {
         device_list.retain(|device| device.scid != scid);
     }
 
-    pub async fn add_device(device_id: String, scid: String, main: bool, socket_ids: Vec<String>) {
+    pub async fn add_device(
+        device_id: String,
+        scid: String,
+        main: bool,
+        video: bool,
+        display_id: i32,
+        socket_ids: Vec<String>,
+    ) {
         let mut device_list = CONTROLLED_DEVICES.write().await;
-        device_list.push(ScrcpyDevice::new(device_id, scid, main, socket_ids));
+        device_list.push(ScrcpyDevice::new(
+            device_id, scid, main, video, display_id, socket_ids,
+        ));
+    }
+
+    pub async fn set_main_device(scid: &str) {
+        let mut device_list = CONTROLLED_DEVICES.write().await;
+        for device in device_list.iter_mut() {
+            let main = device.scid == scid;
+            if device.main == main {
+                continue;
+            }
+            // control connection is kept, only its role changes
+            device.main = main;
+            device.socket_ids = if main {
+                vec!["main_control".to_string()]
+            } else {
+                vec![format!("sub_control_{}", device.scid)]
+            };
+        }
+    }
+
+    /// Mark the main device as a sub device, so that no device is main until one is promoted.
+    pub async fn demote_main_device() {
+        let mut device_list = CONTROLLED_DEVICES.write().await;
+        for device in device_list.iter_mut().filter(|device| device.main) {
+            device.main = false;
+            device.socket_ids = vec![format!("sub_control_{}", device.scid)];
+        }
     }
 
     pub async fn update_device_name(scid: String, name: String) {
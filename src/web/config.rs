@@ -4,15 +4,17 @@ use axum::{
     routing::{get, post},
 };
 use rust_i18n::t;
+use schemars::{Schema, schema_for};
 use serde::Deserialize;
 use tokio::sync::oneshot;
 
 use crate::{
-    config::LocalConfig,
-    mask::mask_command::MaskCommand,
+    config::{DeviceProfile, LocalConfig, RotationMapping, ScrollForwarding},
+    mask::{mapping::utils::Humanize, mask_command::MaskCommand},
     scrcpy::{adb::Adb, media::VideoCodec},
     utils::{
-        IDENTIFIER, check_for_update, mask_win_move_helper,
+        IDENTIFIER, check_for_update, is_safe_file_name, mask_win_move_helper,
+        profile_mapping_helper, relate_to_data_path,
         share::{ControlledDevice, UpdateInfo},
     },
     web::{JsonResponse, WebServerError},
@@ -29,9 +31,14 @@ pub fn routers(
     Router::new()
         .route("/get_config", get(get_config))
         .route("/update_config", post(update_config))
+        .route("/update_device_profile", post(update_device_profile))
+        .route("/delete_device_profile", post(delete_device_profile))
+        .route("/update_device_group", post(update_device_group))
+        .route("/delete_device_group", post(delete_device_group))
         .route("/open_data_path", get(open_data_path))
         .route("/get_update_info", get(get_update_info))
         .route("/check_update", get(check_update))
+        .route("/schema", get(get_config_schema))
         .with_state(AppStatConfig { m_tx })
 }
 
@@ -279,6 +286,18 @@ async fn update_config(
                 t!("web.config.pleaseRequestForOperation", api => "/api/mapping/change_active_mapping")
             )));
         }
+        "device_profiles" => {
+            return Err(WebServerError::bad_request(format!(
+                "{}",
+                t!("web.config.pleaseRequestForOperation", api => "/api/config/update_device_profile")
+            )));
+        }
+        "device_groups" => {
+            return Err(WebServerError::bad_request(format!(
+                "{}",
+                t!("web.config.pleaseRequestForOperation", api => "/api/config/update_device_group")
+            )));
+        }
         "mapping_label_opacity" => {
             if let Some(value) = payload.value.as_f64() {
                 if value <= 1.0 && value >= 0.0 {
@@ -297,6 +316,89 @@ async fn update_config(
                 "web.config.mappingLabelOpacityRange"
             )));
         }
+        "rotation_mapping" => {
+            let Ok(mut value) = serde_json::from_value::<RotationMapping>(payload.value) else {
+                return Err(WebServerError::bad_request(t!(
+                    "web.config.rotationMappingTypeError"
+                )));
+            };
+            for file in value.files_mut() {
+                check_mapping_file(file)?;
+            }
+            LocalConfig::set_rotation_mapping(value);
+            return Ok(JsonResponse::success(
+                t!("web.config.setRotationMappingSuccess"),
+                None,
+            ));
+        }
+        "scroll_forwarding" => {
+            let Ok(value) = serde_json::from_value::<ScrollForwarding>(payload.value) else {
+                return Err(WebServerError::bad_request(t!(
+                    "web.config.scrollForwardingTypeError"
+                )));
+            };
+            if value.scale <= 0. {
+                return Err(WebServerError::bad_request(t!(
+                    "web.config.scrollScaleRange"
+                )));
+            }
+            LocalConfig::set_scroll_forwarding(value);
+            let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+            state
+                .m_tx
+                .send((MaskCommand::LocalConfigChange, oneshot_tx))
+                .unwrap();
+            oneshot_rx.await.unwrap().unwrap();
+            return Ok(JsonResponse::success(
+                t!("web.config.setScrollForwardingSuccess"),
+                None,
+            ));
+        }
+        "humanize" => {
+            let Ok(value) = serde_json::from_value::<Humanize>(payload.value) else {
+                return Err(WebServerError::bad_request(t!(
+                    "web.config.humanizeTypeError"
+                )));
+            };
+            value
+                .validate()
+                .map_err(|e| WebServerError::bad_request(e))?;
+            LocalConfig::set_humanize(value);
+            let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+            state
+                .m_tx
+                .send((MaskCommand::LocalConfigChange, oneshot_tx))
+                .unwrap();
+            oneshot_rx.await.unwrap().unwrap();
+            return Ok(JsonResponse::success(
+                t!("web.config.setHumanizeSuccess"),
+                None,
+            ));
+        }
+        "humanize_seed" => {
+            if payload.value.is_null() || payload.value.is_u64() {
+                let seed = payload.value.as_u64();
+                LocalConfig::set_humanize_seed(seed);
+                // restarts the variations of every mapping from the new seed
+                let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+                state
+                    .m_tx
+                    .send((MaskCommand::LocalConfigChange, oneshot_tx))
+                    .unwrap();
+                oneshot_rx.await.unwrap().unwrap();
+                return Ok(JsonResponse::success(
+                    format!(
+                        "{}: {}",
+                        t!("web.config.setHumanizeSeedSuccess"),
+                        payload.value
+                    ),
+                    None,
+                ));
+            }
+            return Err(WebServerError::bad_request(t!(
+                "web.config.humanizeSeedTypeError"
+            )));
+        }
         "clipboard_sync" => {
             if let Some(value) = payload.value.as_bool() {
                 LocalConfig::set_clipboard_sync(value);
@@ -376,3 +478,157 @@ async fn update_config(
         ))),
     }
 }
+
+// normalize the extension and make sure the mapping file exists
+fn check_mapping_file(file: &mut String) -> Result<(), WebServerError> {
+    if !file.ends_with(".json") {
+        file.push_str(".json");
+    }
+    if !is_safe_file_name(file) {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.nameNotSafe"),
+            file
+        )));
+    }
+    if !relate_to_data_path(["mapping", file.as_str()]).exists() {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.mappingConfigNotExists"),
+            file
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct PostDataUpdateDeviceProfile {
+    device_id: String,
+    profile: DeviceProfile,
+}
+
+async fn update_device_profile(
+    State(state): State<AppStatConfig>,
+    Json(mut payload): Json<PostDataUpdateDeviceProfile>,
+) -> Result<JsonResponse, WebServerError> {
+    if let Some(file) = payload.profile.active_mapping_file.as_mut() {
+        check_mapping_file(file)?;
+    }
+    if let Some(rotation_mapping) = payload.profile.rotation_mapping.as_mut() {
+        for file in rotation_mapping.files_mut() {
+            check_mapping_file(file)?;
+        }
+    }
+
+    if let Some(calibration) = &payload.profile.calibration {
+        if calibration.scale.0 <= 0. || calibration.scale.1 <= 0. {
+            return Err(WebServerError::bad_request(t!(
+                "web.config.calibrationScaleError"
+            )));
+        }
+    }
+
+    LocalConfig::set_device_profile(payload.device_id.clone(), payload.profile);
+    let mut msg = format!(
+        "{}: {}",
+        t!("web.config.setDeviceProfileSuccess"),
+        payload.device_id
+    );
+
+    // apply to main device immediately
+    if let Some(main_device) = ControlledDevice::get_main_device().await {
+        if main_device.device_id == payload.device_id {
+            let (device_w, device_h) = main_device.device_size;
+            let move_msg = mask_win_move_helper(device_w, device_h, &state.m_tx).await;
+            msg = format!("{}. {}", msg, move_msg);
+            profile_mapping_helper(&payload.device_id, &state.m_tx)
+                .await
+                .map_err(|e| WebServerError::bad_request(e))?;
+        }
+    }
+
+    Ok(JsonResponse::success(msg, None))
+}
+
+#[derive(Deserialize)]
+struct PostDataDeviceId {
+    device_id: String,
+}
+
+async fn delete_device_profile(
+    Json(payload): Json<PostDataDeviceId>,
+) -> Result<JsonResponse, WebServerError> {
+    if LocalConfig::remove_device_profile(&payload.device_id) {
+        Ok(JsonResponse::success(
+            format!(
+                "{}: {}",
+                t!("web.config.deleteDeviceProfileSuccess"),
+                payload.device_id
+            ),
+            None,
+        ))
+    } else {
+        Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.config.deviceProfileNotFound"),
+            payload.device_id
+        )))
+    }
+}
+
+#[derive(Deserialize)]
+struct PostDataUpdateDeviceGroup {
+    name: String,
+    devices: Vec<String>,
+}
+
+async fn update_device_group(
+    Json(payload): Json<PostDataUpdateDeviceGroup>,
+) -> Result<JsonResponse, WebServerError> {
+    if payload.name.is_empty() || payload.devices.is_empty() {
+        return Err(WebServerError::bad_request(t!(
+            "web.config.deviceGroupEmpty"
+        )));
+    }
+
+    LocalConfig::set_device_group(payload.name.clone(), payload.devices);
+    Ok(JsonResponse::success(
+        format!(
+            "{}: {}",
+            t!("web.config.setDeviceGroupSuccess"),
+            payload.name
+        ),
+        None,
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataGroupName {
+    name: String,
+}
+
+async fn delete_device_group(
+    Json(payload): Json<PostDataGroupName>,
+) -> Result<JsonResponse, WebServerError> {
+    if LocalConfig::remove_device_group(&payload.name) {
+        Ok(JsonResponse::success(
+            format!(
+                "{}: {}",
+                t!("web.config.deleteDeviceGroupSuccess"),
+                payload.name
+            ),
+            None,
+        ))
+    } else {
+        Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.config.deviceGroupNotFound"),
+            payload.name
+        )))
+    }
+}
+
+/// Served as a plain JSON Schema document, like the mapping schema.
+async fn get_config_schema() -> Json<Schema> {
+    Json(schema_for!(LocalConfig))
+}
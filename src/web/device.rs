@@ -25,8 +25,10 @@ use crate::{
         control_msg::ScrcpyControlMsg,
         controller::ControllerCommand,
     },
-    utils::{relate_to_root_path, share::ControlledDevice},
-    web::{JsonResponse, WebServerError},
+    utils::{
+        mask_win_move_helper, profile_mapping_helper, relate_to_root_path, share::ControlledDevice,
+    },
+    web::{JsonResponse, WebServerError, ws::WebSocketNotification},
 };
 
 #[derive(Debug, Clone)]
@@ -34,24 +36,33 @@ pub struct AppStateDevice {
     cs_tx: broadcast::Sender<ScrcpyControlMsg>,
     d_tx: UnboundedSender<ControllerCommand>,
     m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+    ws_tx: broadcast::Sender<WebSocketNotification>,
 }
 
 pub fn routers(
     cs_tx: broadcast::Sender<ScrcpyControlMsg>,
     d_tx: UnboundedSender<ControllerCommand>,
     m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+    ws_tx: broadcast::Sender<WebSocketNotification>,
 ) -> Router {
     Router::new()
         .route("/device_list", get(device_list))
         .route("/control_device", post(control_device))
         .route("/decontrol_device", post(decontrol_device))
+        .route("/switch_main_device", post(switch_main_device))
+        .route("/control_group", post(control_group))
         .route("/adb_connect", post(adb_connect))
         .route("/adb_pair", post(adb_pair))
         .route("/adb_screenshot", post(adb_screenshot))
         .route("/control/set_display_power", post(set_display_power))
         .route("/control/send_key", post(send_key))
         .route("/control/eval_script", post(eval_script))
-        .with_state(AppStateDevice { cs_tx, d_tx, m_tx })
+        .with_state(AppStateDevice {
+            cs_tx,
+            d_tx,
+            m_tx,
+            ws_tx,
+        })
 }
 
 async fn device_list() -> Result<JsonResponse, WebServerError> {
@@ -93,8 +104,6 @@ async fn control_device(
     let video = payload.video;
     let display_id = payload.display_id;
 
-    let local_config = LocalConfig::get();
-
     let device_list = ControlledDevice::get_device_list().await;
     // check if device is controlled
     if device_list
@@ -107,12 +116,81 @@ async fn control_device(
         ));
     }
 
+    let main = device_list.len() == 0;
+    let scid = start_scrcpy(&state, &device_id, display_id, video, main).await?;
+
+    Ok(JsonResponse::success(
+        t!("web.device.tryStartingScrcpy"),
+        Some(json!({"scid": scid, "device_id": device_id})),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataControlGroup {
+    group: String,
+    display_id: i32,
+    video: bool,
+}
+
+async fn control_group(
+    State(state): State<AppStateDevice>,
+    Json(payload): Json<PostDataControlGroup>,
+) -> Result<JsonResponse, WebServerError> {
+    let Some(group) = LocalConfig::get_device_group(&payload.group) else {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.config.deviceGroupNotFound"),
+            payload.group
+        )));
+    };
+
+    let mut started = Vec::new();
+    for device_id in group {
+        let device_list = ControlledDevice::get_device_list().await;
+        if device_list
+            .iter()
+            .any(|device| device.device_id == device_id)
+        {
+            log::info!(
+                "[WebServe] {}: {}",
+                t!("web.device.alreadyControlled"),
+                device_id
+            );
+            continue;
+        }
+        // the first device becomes main if there is none
+        let main = device_list.len() == 0;
+        match start_scrcpy(&state, &device_id, payload.display_id, payload.video, main).await {
+            Ok(scid) => started.push(json!({"scid": scid, "device_id": device_id})),
+            Err(e) => log::error!("[WebServe] {}: {}", device_id, e.1),
+        }
+    }
+
+    Ok(JsonResponse::success(
+        t!("web.device.tryStartingScrcpy"),
+        Some(json!({"devices": started})),
+    ))
+}
+
+// push and run scrcpy app on device, return the scid
+async fn start_scrcpy(
+    state: &AppStateDevice,
+    device_id: &str,
+    display_id: i32,
+    video: bool,
+    main: bool,
+) -> Result<String, WebServerError> {
+    // apply device profile if exists
+    let local_config = LocalConfig::get_for_device(device_id);
+    // only main device has video connection
+    let video = video && main;
+
     // prepare for scrcpy app
     let scid = gen_scid();
     let version = "2.4";
     let scrcpy_path = relate_to_root_path(["assets", &format!("scrcpy-mask-server-v{}", version)]);
     Device::push(
-        &device_id,
+        device_id,
         scrcpy_path.to_str().unwrap(),
         "/data/local/tmp/scrcpy-server.jar",
     )
@@ -121,7 +199,7 @@ async fn control_device(
 
     let remote = format!("localabstract:scrcpy_{}", scid);
     let local = format!("tcp:{}", local_config.controller_port);
-    Device::reverse(&device_id, &remote, &local).map_err(|e| WebServerError(500, e))?;
+    Device::reverse(device_id, &remote, &local).map_err(|e| WebServerError(500, e))?;
     log::info!(
         "[WebServe] {}",
         t!("web.device.reverseSuccess", remote => remote, local => local)
@@ -144,7 +222,6 @@ async fn control_device(
     args.push("audio=false".to_string());
 
     // create device
-    let main = device_list.len() == 0;
     let mut socket_id: Vec<String> = Vec::new();
     let mut commands: Vec<ControllerCommand> = Vec::new();
     if main {
@@ -176,17 +253,40 @@ async fn control_device(
         commands.push(ControllerCommand::ConnectSubControl(scid.clone()));
     }
 
-    ControlledDevice::add_device(device_id.clone(), scid.clone(), main, socket_id).await;
+    ControlledDevice::add_device(
+        device_id.to_string(),
+        scid.clone(),
+        main,
+        video,
+        display_id,
+        socket_id,
+    )
+    .await;
     // send command to controller server
     for cmd in commands {
         state.d_tx.send(cmd).unwrap();
     }
 
+    // activate the mapping from device profile
+    if main {
+        match profile_mapping_helper(device_id, &state.m_tx).await {
+            Ok(Some(file)) => {
+                log::info!(
+                    "[WebServe] {}: {}",
+                    t!("web.device.profileMappingActivated"),
+                    file
+                )
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("[WebServe] {}", e),
+        }
+    }
+
     // run scrcpy app
     sleep(Duration::from_millis(500)).await;
     log::info!("[WebServe] {}", t!("web.device.startingScrcpyApp"));
 
-    let h = Device::shell_process(&device_id, args);
+    let h = Device::shell_process(device_id, args);
 
     let scid_copy = scid.clone();
     tokio::spawn(async move {
@@ -195,9 +295,115 @@ async fn control_device(
         ControlledDevice::remove_device(&scid_copy).await;
     });
 
+    Ok(scid)
+}
+
+#[derive(Deserialize)]
+struct PostDataSwitchMainDevice {
+    device_id: String,
+    video: bool,
+}
+
+async fn switch_main_device(
+    State(state): State<AppStateDevice>,
+    Json(payload): Json<PostDataSwitchMainDevice>,
+) -> Result<JsonResponse, WebServerError> {
+    let device_list = ControlledDevice::get_device_list().await;
+    let Some(new_main) = device_list
+        .iter()
+        .find(|device| device.device_id == payload.device_id)
+        .cloned()
+    else {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.device.deviceNotFound"),
+            payload.device_id
+        )));
+    };
+    if new_main.main {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.device.alreadyMainDevice"),
+            payload.device_id
+        )));
+    }
+    let old_main = device_list.iter().find(|device| device.main).cloned();
+
+    // demote the old main device, its video connection is torn down
+    if let Some(old_main) = &old_main {
+        state
+            .d_tx
+            .send(ControllerCommand::DemoteMain(old_main.scid.clone()))
+            .unwrap();
+        // demote in the shared list too, or it stays main beside the promoted device
+        ControlledDevice::demote_main_device().await;
+        if old_main.video {
+            // scrcpy server exits with its video socket, so restart it as sub device
+            ControlledDevice::remove_device(&old_main.scid).await;
+            state
+                .d_tx
+                .send(ControllerCommand::ShutdownSub(old_main.scid.clone()))
+                .unwrap();
+            start_scrcpy(
+                &state,
+                &old_main.device_id,
+                old_main.display_id,
+                false,
+                false,
+            )
+            .await?;
+        }
+    }
+
+    // promote the new main device
+    let scid = if payload.video {
+        // video socket can only be created on startup, so restart it as main device
+        ControlledDevice::remove_device(&new_main.scid).await;
+        state
+            .d_tx
+            .send(ControllerCommand::ShutdownSub(new_main.scid.clone()))
+            .unwrap();
+        start_scrcpy(&state, &new_main.device_id, new_main.display_id, true, true).await?
+    } else {
+        ControlledDevice::set_main_device(&new_main.scid).await;
+        state
+            .d_tx
+            .send(ControllerCommand::PromoteSub(new_main.scid.clone()))
+            .unwrap();
+        // move mask to the new main device, restarted device will do it on rotation msg
+        let (device_w, device_h) = new_main.device_size;
+        if device_w > 0 && device_h > 0 {
+            mask_win_move_helper(device_w, device_h, &state.m_tx).await;
+        }
+        if let Err(e) = profile_mapping_helper(&new_main.device_id, &state.m_tx).await {
+            log::warn!("[WebServe] {}", e);
+        }
+        new_main.scid.clone()
+    };
+
+    state
+        .ws_tx
+        .send(WebSocketNotification::MainDeviceChanged {
+            scid: scid.clone(),
+            device_id: new_main.device_id.clone(),
+        })
+        .ok();
+    state
+        .ws_tx
+        .send(WebSocketNotification::ScrcpyDeviceList {
+            devices: ControlledDevice::get_device_list().await,
+        })
+        .ok();
+
+    let msg = format!(
+        "{}: {}",
+        t!("web.device.switchMainDeviceSuccess"),
+        new_main.device_id
+    );
+    log::info!("[WebServe] {}", msg);
     Ok(JsonResponse::success(
-        t!("web.device.tryStartingScrcpy"),
-        Some(json!({"scid": scid, "device_id": device_id})),
+        msg,
+        Some(json!({"scid": scid, "device_id": new_main.device_id})),
     ))
 }
 
@@ -292,42 +498,28 @@ struct PostDataId {
     id: String,
 }
 
-async fn adb_screenshot(
-    Json(payload): Json<PostDataId>,
-) -> Result<impl IntoResponse, WebServerError> {
+/// Capture a png screenshot of the device through adb.
+pub fn capture_screenshot(id: &str) -> Result<Vec<u8>, String> {
     let src = "/data/local/tmp/_screenshot_scrcpy_mask.png";
 
-    Device::shell(
-        &payload.id,
-        ["screencap", "-p", src],
-        &mut std::io::stdout(),
-    )
-    .map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.device.screenshotError"),
-            payload.id,
-            e
-        ))
-    })?;
+    Device::shell(id, ["screencap", "-p", src], &mut std::io::stdout())
+        .map_err(|e| format!("{} {}: {}", t!("web.device.screenshotError"), id, e))?;
 
     let mut image_bytes = Vec::<u8>::new();
-    Device::pull(&payload.id, src.to_string(), &mut image_bytes).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{}: {}",
-            t!("web.device.failedGetScreenshotFile"),
-            e
-        ))
-    })?;
+    Device::pull(id, src.to_string(), &mut image_bytes)
+        .map_err(|e| format!("{}: {}", t!("web.device.failedGetScreenshotFile"), e))?;
 
-    Device::shell(&payload.id, ["rm", src], &mut std::io::stdout()).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.device.failedRemoveScreenshot"),
-            payload.id,
-            e
-        ))
-    })?;
+    Device::shell(id, ["rm", src], &mut std::io::stdout())
+        .map_err(|e| format!("{} {}: {}", t!("web.device.failedRemoveScreenshot"), id, e))?;
+
+    Ok(image_bytes)
+}
+
+async fn adb_screenshot(
+    Json(payload): Json<PostDataId>,
+) -> Result<impl IntoResponse, WebServerError> {
+    let image_bytes =
+        capture_screenshot(&payload.id).map_err(|e| WebServerError::bad_request(e))?;
 
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_static("image/png"));
@@ -2,11 +2,15 @@ use std::fs;
 
 use axum::{
     Json, Router,
-    extract::State,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
     routing::{get, post},
 };
 use bevy::math::Vec2;
 use rust_i18n::t;
+use schemars::{Schema, schema_for};
 use serde::Deserialize;
 use serde_json::json;
 use tokio::sync::oneshot;
@@ -14,13 +18,34 @@ use tokio::sync::oneshot;
 use crate::{
     config::LocalConfig,
     mask::{
-        mapping::config::{MappingConfig, MappingType, save_mapping_config},
+        mapping::{
+            bundle::{
+                BundleRules, ConflictStrategy, export_bundle, install_bundle, preview_path,
+                read_bundle,
+            },
+            check::check_mapping_config,
+            config::{
+                MappingConfig, MappingType, default_mapping_config, read_mapping_config,
+                save_mapping_config,
+            },
+            gesture::GestureKind,
+            history::{
+                MAX_REVISIONS, diff_mapping_configs, list_revisions, read_revision, remove_history,
+                rename_history, save_revision,
+            },
+            keymap::{KeymapFormat, export_keymap, import_keymap},
+            utils::Size,
+            version::MAPPING_CONFIG_VERSION,
+        },
         mask_command::MaskCommand,
     },
     utils::{is_safe_file_name, relate_to_data_path},
-    web::{JsonResponse, WebServerError},
+    web::{JsonResponse, WebServerError, device::capture_screenshot},
 };
 
+// limit of an uploaded bundle archive
+const MAX_BUNDLE_SIZE: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, Clone)]
 pub struct AppStatMapping {
     m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
@@ -39,6 +64,20 @@ pub fn routers(
         .route("/read_mapping", post(read_mapping))
         .route("/get_mapping_list", get(get_mapping_list))
         .route("/migrate_mapping", post(migrate_mapping))
+        .route("/check_mapping", post(check_mapping))
+        .route("/list_revisions", post(list_mapping_revisions))
+        .route("/diff_revisions", post(diff_mapping_revisions))
+        .route("/restore_revision", post(restore_mapping_revision))
+        .route("/import", post(import_mapping))
+        .route("/export", post(export_mapping))
+        .route("/export_bundle", post(export_mapping_bundle))
+        .route(
+            "/import_bundle",
+            post(import_mapping_bundle).layer(DefaultBodyLimit::max(MAX_BUNDLE_SIZE)),
+        )
+        .route("/apply_bundle_rules", post(apply_bundle_rules))
+        .route("/preview", post(get_mapping_preview))
+        .route("/schema", get(get_mapping_schema))
         .with_state(AppStatMapping { m_tx })
 }
 
@@ -144,6 +183,9 @@ async fn create_mapping(
         ));
     }
 
+    // deserialized with the current schema
+    payload.config.version = MAPPING_CONFIG_VERSION.to_string();
+
     validate_config(&state.m_tx, &payload.config)
         .await
         .map_err(|e| WebServerError::bad_request(e))?;
@@ -216,6 +258,10 @@ async fn delete_mapping(
             e
         ))
     })?;
+    fs::remove_file(preview_path(&payload.file)).ok();
+    if let Err(e) = remove_history(&payload.file) {
+        log::warn!("[WebServer] {}", e);
+    }
 
     log::info!(
         "[WebServer] {}: {}",
@@ -286,6 +332,10 @@ async fn rename_mapping(
         ));
     }
     fs::rename(old_path, new_path).map_err(|e| WebServerError::internal_error(e.to_string()))?;
+    fs::rename(preview_path(&payload.file), preview_path(&payload.new_file)).ok();
+    if let Err(e) = rename_history(&payload.file, &payload.new_file) {
+        log::warn!("[WebServer] {}", e);
+    }
 
     // get active mapping file
     let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
@@ -426,11 +476,15 @@ async fn update_mapping(
         ));
     }
 
+    // deserialized with the current schema
+    payload.config.version = MAPPING_CONFIG_VERSION.to_string();
+
     validate_config(&state.m_tx, &payload.config)
         .await
         .map_err(|e| WebServerError::bad_request(e))?;
 
-    // save to file
+    // keep the previous content, then save to file
+    save_revision(&payload.file).map_err(|e| WebServerError::bad_request(e))?;
     let config_path = relate_to_data_path(["mapping", &payload.file]);
     save_mapping_config(&payload.config, &config_path)
         .map_err(|e| WebServerError::bad_request(e))?;
@@ -552,22 +606,8 @@ async fn read_mapping(
             payload.file
         ));
     }
-    let config_string = std::fs::read_to_string(path).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.mapping.cannotReadMappingConfig"),
-            payload.file,
-            e
-        ))
-    })?;
-    let mapping_config: MappingConfig = serde_json::from_str(&config_string).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.mapping.cannotDeserializeConfig"),
-            payload.file,
-            e
-        ))
-    })?;
+    let (mapping_config, migrations) = read_mapping_config(&path)
+        .map_err(|e| WebServerError::bad_request(format!("{}: {}", payload.file, e)))?;
 
     validate_config(&state.m_tx, &mapping_config)
         .await
@@ -584,6 +624,7 @@ async fn read_mapping(
         format!("{} {}", t!("web.mapping.mappingReadSuccess"), payload.file),
         Some(json!({
             "mapping_config": mapping_config,
+            "migrations": migrations,
         })),
     ))
 }
@@ -636,22 +677,8 @@ async fn migrate_mapping(
         ));
     }
 
-    let config_string = std::fs::read_to_string(old_path).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.mapping.cannotReadMappingConfig"),
-            payload.file,
-            e
-        ))
-    })?;
-    let mut mapping_config: MappingConfig = serde_json::from_str(&config_string).map_err(|e| {
-        WebServerError::bad_request(format!(
-            "{} {}: {}",
-            t!("web.mapping.cannotDeserializeConfig"),
-            payload.file,
-            e
-        ))
-    })?;
+    let (mut mapping_config, _) = read_mapping_config(&old_path)
+        .map_err(|e| WebServerError::bad_request(format!("{}: {}", payload.file, e)))?;
 
     if payload.width == 0 || payload.height == 0 {
         return bad_request(format!(
@@ -671,8 +698,7 @@ async fn migrate_mapping(
     mapping_config.original_size.height = payload.height;
 
     mapping_config
-        .mappings
-        .iter_mut()
+        .all_mappings_mut()
         .for_each(|mapping| match mapping {
             MappingType::SingleTap(m) => {
                 m.position *= scale;
@@ -689,6 +715,40 @@ async fn migrate_mapping(
                 m.positions.iter_mut().for_each(|p| {
                     *p *= scale;
                 });
+                m.segments
+                    .iter_mut()
+                    .flat_map(|s| s.control_points.iter_mut())
+                    .for_each(|p| {
+                        *p *= scale;
+                    });
+            }
+            MappingType::Gesture(m) => {
+                m.position *= scale;
+                match &mut m.gesture {
+                    GestureKind::Pinch {
+                        start_radius,
+                        end_radius,
+                        ..
+                    } => {
+                        *start_radius *= scale.y;
+                        *end_radius *= scale.y;
+                    }
+                    GestureKind::Rotate { radius, .. } => {
+                        *radius *= scale.y;
+                    }
+                    GestureKind::Swipe {
+                        offset_x,
+                        offset_y,
+                        spacing,
+                    } => {
+                        *offset_x *= scale.x;
+                        *offset_y *= scale.y;
+                        *spacing *= scale.y;
+                    }
+                }
+            }
+            MappingType::Scroll(m) => {
+                m.position *= scale;
             }
             MappingType::DirectionPad(m) => {
                 m.position *= scale;
@@ -713,6 +773,10 @@ async fn migrate_mapping(
             }
             MappingType::Fps(m) => {
                 m.position *= scale;
+                if let Some(margin) = &mut m.recenter.margin {
+                    *margin *= scale.y;
+                }
+                m.recenter.max_drag *= scale.y;
             }
             MappingType::Fire(m) => {
                 m.position *= scale;
@@ -723,6 +787,9 @@ async fn migrate_mapping(
             MappingType::Script(m) => {
                 m.position *= scale;
             }
+            MappingType::SwitchMapping(m) => {
+                m.position *= scale;
+            }
         });
 
     // save to file
@@ -738,3 +805,375 @@ async fn migrate_mapping(
     log::info!("[WebServer] {}", msg);
     Ok(JsonResponse::success(msg, None))
 }
+
+#[derive(Deserialize)]
+struct PostDataCheckMapping {
+    config: MappingConfig,
+}
+
+async fn check_mapping(
+    Json(payload): Json<PostDataCheckMapping>,
+) -> Result<JsonResponse, WebServerError> {
+    let issues = check_mapping_config(&payload.config);
+    Ok(JsonResponse::success(
+        t!("web.mapping.checkMappingConfig"),
+        Some(json!({
+            "issues": issues,
+        })),
+    ))
+}
+
+fn check_mapping_file_name(file: &mut String) -> Result<(), WebServerError> {
+    if !file.ends_with(".json") {
+        file.push_str(".json");
+    }
+    if !is_safe_file_name(file) {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.nameNotSafe"),
+            file
+        )));
+    }
+    Ok(())
+}
+
+async fn list_mapping_revisions(
+    Json(mut payload): Json<PostDataMappingFile>,
+) -> Result<JsonResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let revisions = list_revisions(&payload.file).map_err(|e| WebServerError::bad_request(e))?;
+    Ok(JsonResponse::success(
+        format!(
+            "{}: {}",
+            t!("web.mapping.listRevisionsSuccess"),
+            payload.file
+        ),
+        Some(json!({
+            "revisions": revisions,
+            "max_revisions": MAX_REVISIONS,
+        })),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataDiffRevisions {
+    file: String,
+    // revision ids, or "current" for the current file
+    from: String,
+    to: String,
+}
+
+async fn diff_mapping_revisions(
+    Json(mut payload): Json<PostDataDiffRevisions>,
+) -> Result<JsonResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let before =
+        read_revision(&payload.file, &payload.from).map_err(|e| WebServerError::bad_request(e))?;
+    let after =
+        read_revision(&payload.file, &payload.to).map_err(|e| WebServerError::bad_request(e))?;
+    let diff = diff_mapping_configs(&before, &after);
+    Ok(JsonResponse::success(
+        format!(
+            "{}: {}",
+            t!("web.mapping.diffRevisionsSuccess"),
+            payload.file
+        ),
+        Some(json!({
+            "changes": diff.mappings,
+            "layers": diff.layers,
+            "original_size": diff.original_size,
+        })),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataRestoreRevision {
+    file: String,
+    revision: String,
+}
+
+async fn restore_mapping_revision(
+    State(state): State<AppStatMapping>,
+    Json(mut payload): Json<PostDataRestoreRevision>,
+) -> Result<JsonResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let mapping_config = read_revision(&payload.file, &payload.revision)
+        .map_err(|e| WebServerError::bad_request(e))?;
+    validate_config(&state.m_tx, &mapping_config)
+        .await
+        .map_err(|e| WebServerError::bad_request(e))?;
+
+    // the current content is kept as well, so that restoring can be undone
+    save_revision(&payload.file).map_err(|e| WebServerError::bad_request(e))?;
+    let config_path = relate_to_data_path(["mapping", &payload.file]);
+    save_mapping_config(&mapping_config, &config_path)
+        .map_err(|e| WebServerError::bad_request(e))?;
+
+    let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+    state
+        .m_tx
+        .send((MaskCommand::GetActiveMapping, oneshot_tx))
+        .unwrap();
+    if oneshot_rx.await.unwrap().unwrap() == payload.file {
+        let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+        state
+            .m_tx
+            .send((
+                MaskCommand::LoadAndActivateMappingConfig {
+                    file_name: payload.file.clone(),
+                },
+                oneshot_tx,
+            ))
+            .unwrap();
+        oneshot_rx.await.unwrap().map_err(|e| {
+            WebServerError::bad_request(format!(
+                "{} {}. {}",
+                t!("web.mapping.failedToLoadUpdatedMappingConfig"),
+                payload.file,
+                e
+            ))
+        })?;
+    }
+
+    let msg = format!(
+        "{}: {} ({})",
+        t!("web.mapping.restoreRevisionSuccess"),
+        payload.file,
+        payload.revision
+    );
+    log::info!("[WebServer] {}", msg);
+    Ok(JsonResponse::success(msg, None))
+}
+
+#[derive(Deserialize)]
+struct PostDataImportMapping {
+    file: String,
+    format: KeymapFormat,
+    keymap: serde_json::Value,
+    // used by formats with relative positions
+    #[serde(default)]
+    original_size: Option<Size>,
+}
+
+async fn import_mapping(
+    State(state): State<AppStatMapping>,
+    Json(mut payload): Json<PostDataImportMapping>,
+) -> Result<JsonResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let config_path = relate_to_data_path(["mapping", &payload.file]);
+    if config_path.exists() {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.mappingConfigExists"),
+            payload.file
+        )));
+    }
+
+    let original_size = payload
+        .original_size
+        .unwrap_or_else(|| default_mapping_config().original_size);
+    let (mapping_config, skipped) = import_keymap(payload.format, payload.keymap, original_size)
+        .map_err(|e| WebServerError::bad_request(e))?;
+    validate_config(&state.m_tx, &mapping_config)
+        .await
+        .map_err(|e| WebServerError::bad_request(e))?;
+    save_mapping_config(&mapping_config, &config_path)
+        .map_err(|e| WebServerError::bad_request(e))?;
+
+    let msg = format!(
+        "{}: {}",
+        t!("web.mapping.importMappingSuccess", count => skipped.len()),
+        payload.file
+    );
+    log::info!("[WebServer] {}", msg);
+    Ok(JsonResponse::success(
+        msg,
+        Some(json!({
+            "skipped": skipped,
+        })),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataExportMapping {
+    file: String,
+    format: KeymapFormat,
+}
+
+async fn export_mapping(
+    Json(mut payload): Json<PostDataExportMapping>,
+) -> Result<JsonResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let config_path = relate_to_data_path(["mapping", &payload.file]);
+    if !config_path.exists() {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.mappingConfigNotExists"),
+            payload.file
+        )));
+    }
+
+    let (mapping_config, _) =
+        read_mapping_config(&config_path).map_err(|e| WebServerError::bad_request(e))?;
+    let (keymap, skipped) = export_keymap(payload.format, &mapping_config)
+        .map_err(|e| WebServerError::bad_request(e))?;
+    Ok(JsonResponse::success(
+        format!(
+            "{}: {}",
+            t!("web.mapping.exportMappingSuccess", count => skipped.len()),
+            payload.file
+        ),
+        Some(json!({
+            "keymap": keymap,
+            "skipped": skipped,
+        })),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostDataExportBundle {
+    files: Vec<String>,
+    #[serde(default)]
+    name: Option<String>,
+    // device to capture the preview from, for mappings without a stored preview
+    #[serde(default)]
+    preview_device: Option<String>,
+}
+
+async fn export_mapping_bundle(
+    Json(mut payload): Json<PostDataExportBundle>,
+) -> Result<impl IntoResponse, WebServerError> {
+    if payload.files.is_empty() {
+        return Err(WebServerError::bad_request(t!(
+            "web.mapping.bundleFilesEmpty"
+        )));
+    }
+    for file in payload.files.iter_mut() {
+        check_mapping_file_name(file)?;
+    }
+
+    let preview = match &payload.preview_device {
+        Some(id) => Some(capture_screenshot(id).map_err(|e| WebServerError::bad_request(e))?),
+        None => None,
+    };
+    let name = payload
+        .name
+        .clone()
+        .unwrap_or_else(|| payload.files[0].trim_end_matches(".json").to_string());
+    if !is_safe_file_name(&name) {
+        return Err(WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.nameNotSafe"),
+            name
+        )));
+    }
+    let data = export_bundle(&name, &payload.files, preview)
+        .map_err(|e| WebServerError::bad_request(e))?;
+
+    log::info!(
+        "[WebServer] {}: {}",
+        t!("web.mapping.exportBundleSuccess"),
+        payload.files.join(", ")
+    );
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/zip"));
+    headers.insert(
+        "Content-Disposition",
+        HeaderValue::from_str(&format!("attachment; filename=\"{}.zip\"", name))
+            .unwrap_or(HeaderValue::from_static("attachment")),
+    );
+    Ok((StatusCode::OK, headers, data))
+}
+
+#[derive(Deserialize)]
+struct QueryImportBundle {
+    #[serde(default)]
+    conflict: ConflictStrategy,
+    // rules change app rules and device profiles, so they are applied only when asked
+    #[serde(default)]
+    apply_rules: bool,
+}
+
+/// The archive is posted as the raw request body, options are given in the query.
+async fn import_mapping_bundle(
+    State(state): State<AppStatMapping>,
+    Query(query): Query<QueryImportBundle>,
+    body: Bytes,
+) -> Result<JsonResponse, WebServerError> {
+    let bundle = read_bundle(&body).map_err(|e| WebServerError::bad_request(e))?;
+    for (mapping, config) in bundle.manifest.mappings.iter().zip(bundle.configs.iter()) {
+        validate_config(&state.m_tx, config)
+            .await
+            .map_err(|e| WebServerError::bad_request(format!("{}: {}", mapping.file, e)))?;
+    }
+
+    let name = bundle.manifest.name.clone();
+    let installed = install_bundle(bundle, query.conflict, query.apply_rules)
+        .map_err(|e| WebServerError::bad_request(e))?;
+
+    let msg = format!("{}: {}", t!("web.mapping.importBundleSuccess"), name);
+    log::info!("[WebServer] {}", msg);
+    Ok(JsonResponse::success(
+        msg,
+        Some(json!({
+            "mappings": installed.mappings,
+            "rules": installed.rules,
+            "rules_applied": installed.rules_applied,
+            "device_profiles": installed.device_profiles,
+        })),
+    ))
+}
+
+/// Apply the rules returned by `import_bundle` after the user confirmed them.
+async fn apply_bundle_rules(
+    Json(mut rules): Json<BundleRules>,
+) -> Result<JsonResponse, WebServerError> {
+    for file in rules
+        .app_rules
+        .iter_mut()
+        .map(|rule| &mut rule.mapping_file)
+        .chain(rules.device_mappings.values_mut())
+    {
+        check_mapping_file_name(file)?;
+        if !relate_to_data_path(["mapping", file]).exists() {
+            return Err(WebServerError::bad_request(format!(
+                "{}: {}",
+                t!("web.mapping.mappingConfigNotExists"),
+                file
+            )));
+        }
+    }
+    let device_profiles = rules.apply().map_err(|e| WebServerError::bad_request(e))?;
+
+    let msg = t!("web.mapping.applyBundleRulesSuccess").to_string();
+    log::info!("[WebServer] {}", msg);
+    Ok(JsonResponse::success(
+        msg,
+        Some(json!({
+            "device_profiles": device_profiles,
+        })),
+    ))
+}
+
+async fn get_mapping_preview(
+    Json(mut payload): Json<PostDataMappingFile>,
+) -> Result<impl IntoResponse, WebServerError> {
+    check_mapping_file_name(&mut payload.file)?;
+    let image_bytes = fs::read(preview_path(&payload.file)).map_err(|_| {
+        WebServerError::bad_request(format!(
+            "{}: {}",
+            t!("web.mapping.previewNotFound"),
+            payload.file
+        ))
+    })?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("image/png"));
+    headers.insert("Cache-Control", HeaderValue::from_static("no-cache"));
+    Ok((StatusCode::OK, headers, image_bytes))
+}
+
+/// Served as a plain JSON Schema document, so that editors can reference the url directly.
+async fn get_mapping_schema() -> Json<Schema> {
+    Json(schema_for!(MappingConfig))
+}
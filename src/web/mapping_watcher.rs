@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use rust_i18n::t;
+use tokio::{
+    sync::{broadcast, oneshot},
+    time::sleep,
+};
+
+use crate::{
+    mask::{mapping::config::is_saved_by_app, mask_command::MaskCommand},
+    utils::relate_to_data_path,
+    web::ws::WebSocketNotification,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+async fn send_mask_command(
+    m_tx: &crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+    command: MaskCommand,
+) -> Result<String, String> {
+    let (oneshot_tx, oneshot_rx) = oneshot::channel::<Result<String, String>>();
+    m_tx.send((command, oneshot_tx)).unwrap();
+    oneshot_rx.await.unwrap()
+}
+
+/// Reload the active mapping file when it is modified on disk by others.
+/// Saves of the app itself are skipped, as they activate the config already.
+/// The mask keeps the previous config if the new one fails to load.
+pub async fn watch_mapping_files(
+    m_tx: crossbeam_channel::Sender<(MaskCommand, oneshot::Sender<Result<String, String>>)>,
+    ws_tx: broadcast::Sender<WebSocketNotification>,
+) {
+    let mut last: Option<(String, SystemTime)> = None;
+    loop {
+        sleep(POLL_INTERVAL).await;
+
+        let Ok(file) = send_mask_command(&m_tx, MaskCommand::GetActiveMapping).await else {
+            continue;
+        };
+        let path = relate_to_data_path(["mapping", &file]);
+        let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            continue;
+        };
+
+        let changed = match &last {
+            // only reload if the same file is modified, not when another one is activated
+            Some((last_file, last_modified)) => {
+                *last_file == file
+                    && *last_modified != modified
+                    && !is_saved_by_app(&path, modified)
+            }
+            None => false,
+        };
+        last = Some((file.clone(), modified));
+        if !changed {
+            continue;
+        }
+
+        let result = send_mask_command(
+            &m_tx,
+            MaskCommand::LoadAndActivateMappingConfig {
+                file_name: file.clone(),
+            },
+        )
+        .await;
+        let message = match &result {
+            Ok(_) => {
+                let msg = format!("{}: {}", t!("web.mapping.mappingHotReloaded"), file);
+                log::info!("[WebServer] {}", msg);
+                msg
+            }
+            Err(e) => {
+                let msg = format!(
+                    "{}: {}. {}",
+                    t!("web.mapping.mappingHotReloadFailed"),
+                    file,
+                    e
+                );
+                log::warn!("[WebServer] {}", msg);
+                msg
+            }
+        };
+        ws_tx
+            .send(WebSocketNotification::MappingReloaded {
+                file,
+                success: result.is_ok(),
+                message,
+            })
+            .ok();
+    }
+}
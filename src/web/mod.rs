@@ -1,6 +1,7 @@
 pub mod config;
 pub mod device;
 pub mod mapping;
+pub mod mapping_watcher;
 pub mod ws;
 
 use axum::{
@@ -70,6 +71,13 @@ impl Server {
             log::error!("[WebServe] {}: {}", t!("web.server.failedToOpenBrowser"), e)
         });
 
+        // reload the active mapping when it is edited by hand
+        let m_tx_copy = m_tx.clone();
+        let ws_tx_copy = ws_tx.clone();
+        tokio::spawn(
+            async move { mapping_watcher::watch_mapping_files(m_tx_copy, ws_tx_copy).await },
+        );
+
         axum::serve(listener, Self::app(cs_tx, d_tx, m_tx, ws_tx))
             .await
             .unwrap();
@@ -89,7 +97,7 @@ impl Server {
             )
             .nest(
                 "/api/device",
-                device::routers(cs_tx.clone(), d_tx, m_tx.clone()),
+                device::routers(cs_tx.clone(), d_tx, m_tx.clone(), ws_tx.clone()),
             )
             .nest("/api/mapping", mapping::routers(m_tx.clone()))
             .nest("/api/config", config::routers(m_tx.clone()))
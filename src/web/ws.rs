@@ -37,6 +37,20 @@ pub enum WebSocketNotification {
     ScrcpyDeviceList {
         devices: Vec<ScrcpyDevice>,
     },
+    MainDeviceChanged {
+        scid: String,
+        device_id: String,
+    },
+    AppMappingSwitched {
+        package: String,
+        activity: String,
+        file: String,
+    },
+    MappingReloaded {
+        file: String,
+        success: bool,
+        message: String,
+    },
 }
 
 impl From<WebSocketNotification> for Message {